@@ -1,15 +1,144 @@
+use std::fmt::Write;
 use std::{env, fs, path::Path};
 
+use rsf::ast;
+use rsf::common::{FieldMode, FieldType, Number};
 use rsf::rust_codegen::AddrType;
 
+const TF2_RSF: &str = "../rsf/tf2.rsf";
+
+// The rsf language only has "ro", "rw" and "wo" field modes.  xml2rsf
+// records the finer-grained modes in a field's doc comment, as a line of the
+// form "access: <mode>".  That line is passed to regs::map separately from
+// the rest of the doc comment.
+const ACCESS_DOC: &str = "access:";
+
+fn num(n: &Number) -> u32 {
+    u32::try_from(n.value).expect("number doesn't fit in 32 bits")
+}
+
+fn num64(n: &Number) -> u64 {
+    u64::try_from(n.value).expect("number doesn't fit in 64 bits")
+}
+
+// Join the lines of a doc comment, less any access mode, which is returned
+// separately.
+fn split_doc(doc: &[String]) -> (String, Option<String>) {
+    let access = doc
+        .iter()
+        .find_map(|d| d.trim().strip_prefix(ACCESS_DOC))
+        .map(|a| a.trim().to_string());
+    let doc: Vec<&str> = doc
+        .iter()
+        .map(|d| d.trim())
+        .filter(|d| !d.starts_with(ACCESS_DOC))
+        .collect();
+    (doc.join(" "), access)
+}
+
+fn register_def(out: &mut String, r: &ast::Register) {
+    let name = &r.id.name;
+    let (doc, _) = split_doc(&r.doc);
+    let width = num(&r.width);
+    let reset = r.reset_value.as_ref().map(num64);
+    writeln!(
+        out,
+        "RegisterDef {{ name: {name:?}, doc: {doc:?}, width: {width}, \
+         reset: {reset:?}, fields: &["
+    )
+    .unwrap();
+    for f in &r.fields {
+        let FieldType::Bitfield { width } = &f.typ else {
+            panic!("{name}.{}: unsupported field type", f.id.name);
+        };
+        let mode = match f.mode {
+            FieldMode::ReadOnly => "ReadOnly",
+            FieldMode::ReadWrite => "ReadWrite",
+            FieldMode::WriteOnly => "WriteOnly",
+        };
+        let (doc, access) = split_doc(&f.doc);
+        writeln!(
+            out,
+            "FieldDef {{ name: {:?}, doc: {doc:?}, mode: FieldMode::{mode}, \
+             access: {access:?}, lsb: {}, width: {} }},",
+            f.id.name,
+            num(&f.offset),
+            num(width)
+        )
+        .unwrap();
+    }
+    out.push_str("] },\n");
+}
+
+fn block_def(out: &mut String, b: &ast::Block) {
+    let (doc, _) = split_doc(&b.doc);
+    writeln!(
+        out,
+        "BlockDef {{ name: {:?}, doc: {doc:?}, elements: &[",
+        b.id.name
+    )
+    .unwrap();
+    for e in &b.elements {
+        let (id, typ, array) = match &e.component {
+            ast::Component::Single { id, typ } => (id, typ, None),
+            ast::Component::Array { id, typ, length, spacing } => {
+                (id, typ, Some((num(length), num(spacing))))
+            }
+        };
+        let (doc, _) = split_doc(&e.doc);
+        let typ = &typ.path.last().expect("empty type path").name;
+        writeln!(
+            out,
+            "ElementDef {{ name: {:?}, doc: {doc:?}, typ: {typ:?}, \
+             offset: {}, array: {array:?} }},",
+            id.name,
+            num(&e.offset)
+        )
+        .unwrap();
+    }
+    out.push_str("] },\n");
+}
+
+// Generate the tables from which regs::map builds its view of the register
+// hierarchy.
+fn map_tables(ast: &ast::Ast) -> String {
+    let mut out = String::from("// Generated from tf2.rsf by build.rs\n\n");
+    out.push_str("static BLOCKS: &[BlockDef] = &[\n");
+    for b in &ast.blocks {
+        block_def(&mut out, b);
+    }
+    out.push_str("];\n\nstatic REGISTERS: &[RegisterDef] = &[\n");
+    for r in &ast.registers {
+        register_def(&mut out, r);
+    }
+    out.push_str("];\n");
+    out
+}
+
 fn main() {
     let code =
-        rsf::rust_codegen::codegen("../rsf/tf2.rsf".into(), AddrType::U32)
-            .unwrap();
+        rsf::rust_codegen::codegen(TF2_RSF.into(), AddrType::U32).unwrap();
 
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("tf2_rpi.rs");
     fs::write(&dest_path, code).unwrap();
 
-    println!("cargo::rerun-if-changed=../rsf/tf2.rsf")
+    let text = fs::read_to_string(TF2_RSF).unwrap();
+    let ast = rsf::parse::parse(&text).unwrap();
+    let annotated = ast
+        .registers
+        .iter()
+        .flat_map(|r| &r.fields)
+        .any(|f| split_doc(&f.doc).1.is_some());
+    if !annotated {
+        println!(
+            "cargo::warning={TF2_RSF} has no access annotations, so no field \
+             is known to be write-1-to-clear or clear-on-read.  Regenerate \
+             it with xml2rsf."
+        );
+    }
+    let dest_path = Path::new(&out_dir).join("tf2_map.rs");
+    fs::write(&dest_path, map_tables(&ast)).unwrap();
+
+    println!("cargo::rerun-if-changed={TF2_RSF}")
 }
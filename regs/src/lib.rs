@@ -17,4 +17,6 @@
 
 //! RSF generated tofino register programming interface (RPI)
 
+pub mod map;
+
 include!(concat!(env!("OUT_DIR"), "/tf2_rpi.rs"));
@@ -4,13 +4,32 @@
 
 // Copyright 2023 Oxide Computer Company
 
-use anyhow::{Result, anyhow};
-use paste::paste;
-use rust_rpi::RegisterInstance;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow, bail};
+use regs::map::{RegMap, join_path};
+use serde::Serialize;
 
 use crate::*;
 
-#[derive(Debug)]
+/// The registers every descriptor ring's block holds
+const DR_REGS: [&str; 11] = [
+    "ctrl",
+    "base_addr_low",
+    "base_addr_high",
+    "limit_addr_low",
+    "limit_addr_high",
+    "size",
+    "head_ptr",
+    "tail_ptr",
+    "ring_timeout",
+    "data_timeout",
+    "status",
+];
+
+#[derive(Debug, Serialize)]
 struct Dr {
     ctrl: u32,
     base_addr_low: u32,
@@ -25,112 +44,172 @@ struct Dr {
     status: u32,
 }
 
-macro_rules! read_dr_common {
-    ($dr:ident, $ctx:ident) => {
-        Dr {
-            ctrl: u32::from($dr.ctrl().read($ctx)?),
-            base_addr_low: u32::from($dr.base_addr_low().read($ctx)?),
-            base_addr_high: u32::from($dr.base_addr_high().read($ctx)?),
-            limit_addr_low: u32::from($dr.limit_addr_low().read($ctx)?),
-            limit_addr_high: u32::from($dr.limit_addr_high().read($ctx)?),
-            size: u32::from($dr.size().read($ctx)?),
-            head_ptr: u32::from($dr.head_ptr().read($ctx)?),
-            tail_ptr: u32::from($dr.tail_ptr().read($ctx)?),
-            ring_timeout: u32::from($dr.ring_timeout().read($ctx)?),
-            data_timeout: u32::from($dr.data_timeout().read($ctx)?),
-            status: u32::from($dr.status().read($ctx)?),
-        }
-    };
+impl Dr {
+    fn base(&self) -> u64 {
+        (self.base_addr_high as u64) << 32 | self.base_addr_low as u64
+    }
+
+    fn limit(&self) -> u64 {
+        (self.limit_addr_high as u64) << 32 | self.limit_addr_low as u64
+    }
+
+    fn size_matches(&self) -> bool {
+        self.limit().wrapping_sub(self.base()) == self.size as u64
+    }
 }
 
-macro_rules! read_dr_idx {
-    ($block:ident, $name:ident) => {
-        paste! {
-        fn [< read_ $name>](ctx: &mut Tofino, index: u32) -> Result<Dr> {
-            let dr =
-                regs::Client::default().device_select().$block().$name(index)?;
-            Ok(read_dr_common!(dr, ctx))
-        }
-        }
-    };
+/// A descriptor ring found in the register map
+#[derive(Clone, Debug, PartialEq)]
+struct Ring {
+    name: String,
+    path: String,
 }
 
-macro_rules! read_dr {
-    ($block:ident, $name:ident) => {
-        paste! {
-        fn [<read_ $name>](ctx: &mut Tofino) -> Result<Dr> {
-            let dr = regs::Client::default().device_select().$block().$name();
-            Ok(read_dr_common!(dr, ctx))
-        }
+// Find every descriptor ring on every bus.  A ring is named after its block,
+// without the "_dr" suffix and followed by any array index, as in
+// "pbc_il_tx_0" or "cbc_lq_fm".  The TBUS rings keep the "tbus_*" names they
+// have always had, rather than "tbc_*".
+fn rings(map: &RegMap) -> Result<Vec<Ring>> {
+    let mut found = Vec::new();
+    map.walk("device_select", &mut |path, _node| {
+        let Some(ring) = path.strip_suffix(".base_addr_low") else {
+            return Ok(());
+        };
+        if DR_REGS.iter().any(|r| map.get_node(&join_path(ring, r)).is_err()) {
+            return Ok(());
         }
+        let (parent, leaf) = ring.rsplit_once('.').unwrap_or(("", ring));
+        let name = match leaf.parse::<u32>() {
+            Ok(idx) => {
+                let block = parent.rsplit('.').next().unwrap_or(parent);
+                format!("{}_{idx}", block.trim_end_matches("_dr"))
+            }
+            Err(_) => leaf.trim_end_matches("_dr").to_string(),
+        };
+        let name = match name.strip_prefix("tbc_") {
+            Some(n) => format!("tbus_{n}"),
+            None => name,
+        };
+        found.push(Ring { name, path: ring.to_string() });
+        Ok(())
+    })?;
+    Ok(found)
+}
+
+// Find a ring by name or by path.  The TBUS rings may also be named after
+// their blocks, as "tbc_*".
+fn find_ring(rings: &[Ring], name: &str) -> Result<Ring> {
+    let alias = name.strip_prefix("tbc_").map(|n| format!("tbus_{n}"));
+    rings
+        .iter()
+        .find(|r| {
+            r.name == name || r.path == name || Some(&r.name) == alias.as_ref()
+        })
+        .cloned()
+        .ok_or(anyhow!("no such descriptor ring: {name}"))
+}
+
+fn read_dr(ctx: &Tofino, ring: &Ring) -> Result<Dr> {
+    let read = |reg: &str| {
+        ctx.pci.read4(ctx.map.get_offset(&join_path(&ring.path, reg))?)
     };
+    Ok(Dr {
+        ctrl: read("ctrl")?,
+        base_addr_low: read("base_addr_low")?,
+        base_addr_high: read("base_addr_high")?,
+        limit_addr_low: read("limit_addr_low")?,
+        limit_addr_high: read("limit_addr_high")?,
+        size: read("size")?,
+        head_ptr: read("head_ptr")?,
+        tail_ptr: read("tail_ptr")?,
+        ring_timeout: read("ring_timeout")?,
+        data_timeout: read("data_timeout")?,
+        status: read("status")?,
+    })
 }
 
-// TBUS descriptor rings
-read_dr_idx!(tbc, tbc_tx_dr);
-read_dr_idx!(tbc, tbc_cpl_dr);
-read_dr_idx!(tbc, tbc_fm_dr);
-read_dr_idx!(tbc, tbc_rx_dr);
-
-// pipe instruction list rings
-read_dr_idx!(pbc, pbc_il_tx_dr);
-read_dr_idx!(pbc, pbc_il_cpl_dr);
-
-// pipe instruction read/write block rings
-read_dr!(pbc, pbc_rb_tx_dr);
-read_dr!(pbc, pbc_rb_cpl_dr);
-read_dr!(pbc, pbc_wb_tx_dr);
-read_dr!(pbc, pbc_wb_cpl_dr);
-
-#[allow(clippy::type_complexity)]
-const DR_FNS: [(&str, fn(&mut Tofino) -> Result<Dr>); 36] = [
-    ("tbus_tx_0", |ctx| read_tbc_tx_dr(ctx, 0)),
-    ("tbus_tx_1", |ctx| read_tbc_tx_dr(ctx, 1)),
-    ("tbus_tx_2", |ctx| read_tbc_tx_dr(ctx, 2)),
-    ("tbus_tx_3", |ctx| read_tbc_tx_dr(ctx, 3)),
-    ("tbus_cpl_0", |ctx| read_tbc_cpl_dr(ctx, 0)),
-    ("tbus_cpl_1", |ctx| read_tbc_cpl_dr(ctx, 1)),
-    ("tbus_cpl_2", |ctx| read_tbc_cpl_dr(ctx, 2)),
-    ("tbus_cpl_3", |ctx| read_tbc_cpl_dr(ctx, 3)),
-    ("tbus_fm_0", |ctx| read_tbc_fm_dr(ctx, 0)),
-    ("tbus_fm_1", |ctx| read_tbc_fm_dr(ctx, 1)),
-    ("tbus_fm_2", |ctx| read_tbc_fm_dr(ctx, 2)),
-    ("tbus_fm_3", |ctx| read_tbc_fm_dr(ctx, 3)),
-    ("tbus_fm_4", |ctx| read_tbc_fm_dr(ctx, 4)),
-    ("tbus_fm_5", |ctx| read_tbc_fm_dr(ctx, 5)),
-    ("tbus_fm_6", |ctx| read_tbc_fm_dr(ctx, 6)),
-    ("tbus_fm_7", |ctx| read_tbc_fm_dr(ctx, 7)),
-    ("tbus_rx_0", |ctx| read_tbc_rx_dr(ctx, 0)),
-    ("tbus_rx_1", |ctx| read_tbc_rx_dr(ctx, 1)),
-    ("tbus_rx_2", |ctx| read_tbc_rx_dr(ctx, 2)),
-    ("tbus_rx_3", |ctx| read_tbc_rx_dr(ctx, 3)),
-    ("tbus_rx_4", |ctx| read_tbc_rx_dr(ctx, 4)),
-    ("tbus_rx_5", |ctx| read_tbc_rx_dr(ctx, 5)),
-    ("tbus_rx_6", |ctx| read_tbc_rx_dr(ctx, 6)),
-    ("tbus_rx_7", |ctx| read_tbc_rx_dr(ctx, 7)),
-    ("pbc_il_tx_0", |ctx| read_pbc_il_tx_dr(ctx, 0)),
-    ("pbc_il_tx_1", |ctx| read_pbc_il_tx_dr(ctx, 1)),
-    ("pbc_il_tx_2", |ctx| read_pbc_il_tx_dr(ctx, 2)),
-    ("pbc_il_tx_3", |ctx| read_pbc_il_tx_dr(ctx, 3)),
-    ("pbc_il_cpl_0", |ctx| read_pbc_il_cpl_dr(ctx, 0)),
-    ("pbc_il_cpl_1", |ctx| read_pbc_il_cpl_dr(ctx, 1)),
-    ("pbc_il_cpl_2", |ctx| read_pbc_il_cpl_dr(ctx, 2)),
-    ("pbc_il_cpl_3", |ctx| read_pbc_il_cpl_dr(ctx, 3)),
-    ("pbc_rb_tx", |ctx| read_pbc_rb_tx_dr(ctx)),
-    ("pbc_rb_cpl", |ctx| read_pbc_rb_cpl_dr(ctx)),
-    ("pbc_wb_tx", |ctx| read_pbc_wb_tx_dr(ctx)),
-    ("pbc_wb_cpl", |ctx| read_pbc_wb_cpl_dr(ctx)),
-];
+/// A field of a ring's ctrl or status register
+#[derive(Serialize)]
+struct DrField {
+    name: String,
+    value: u64,
+}
 
-fn show(ctx: &mut Tofino, name: &str) -> Result<()> {
-    let read_fn = DR_FNS
+// Decode one of a ring's registers into its named fields, leaving out any
+// reserved bits.
+fn decode(
+    map: &RegMap,
+    ring: &Ring,
+    reg: &str,
+    value: u32,
+) -> Result<Vec<DrField>> {
+    let path = join_path(&ring.path, reg);
+    let node = map.get_node(&path)?;
+    let def = node.register().ok_or(anyhow!("{path} is not a register"))?;
+    Ok(def
+        .fields
         .iter()
-        .find(|(n, _f)| &name == n)
-        .map(|(_n, f)| f)
-        .ok_or(anyhow!(format!("no such descriptor ring: {}", name)))?;
+        .filter(|f| !f.name.starts_with("rsvd"))
+        .map(|f| DrField {
+            name: f.name.clone(),
+            value: f.extract(value as u64),
+        })
+        .collect())
+}
+
+/// The JSON form of a descriptor ring, used by both `dr show` and `dr dump`
+/// (which emits an array of them).  Alongside the raw register values, it
+/// holds the 64-bit `base` and `limit` addresses, whether the span between
+/// them agrees with the `size` register, and the decoded fields of `ctrl`
+/// and `status`.
+#[derive(Serialize)]
+struct DrReport {
+    name: String,
+    path: String,
+    #[serde(flatten)]
+    dr: Dr,
+    base: u64,
+    limit: u64,
+    size_matches: bool,
+    ctrl_fields: Vec<DrField>,
+    status_fields: Vec<DrField>,
+}
+
+impl DrReport {
+    fn read(ctx: &Tofino, ring: &Ring) -> Result<Self> {
+        let dr = read_dr(ctx, ring)?;
+        let ctrl_fields = decode(&ctx.map, ring, "ctrl", dr.ctrl)?;
+        let status_fields = decode(&ctx.map, ring, "status", dr.status)?;
+        Ok(DrReport {
+            name: ring.name.clone(),
+            path: ring.path.clone(),
+            base: dr.base(),
+            limit: dr.limit(),
+            size_matches: dr.size_matches(),
+            dr,
+            ctrl_fields,
+            status_fields,
+        })
+    }
+}
 
-    let dr = read_fn(ctx)?;
-    println!("ctrl: {:08x}", dr.ctrl);
+fn fields_line(fields: &[DrField]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("{}={:#x}", f.name, f.value))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn show(ctx: &Tofino, name: &str, format: Format) -> Result<()> {
+    let ring = find_ring(&rings(&ctx.map)?, name)?;
+    let report = DrReport::read(ctx, &ring)?;
+    if format == Format::Json {
+        return print_json(&report);
+    }
+    let dr = &report.dr;
+    println!("{} ({})", report.name, report.path);
+    println!("ctrl: {:08x} {}", dr.ctrl, fields_line(&report.ctrl_fields));
     println!("base_addr_low: {:08x}", dr.base_addr_low);
     println!("base_addr_high: {:08x}", dr.base_addr_high);
     println!("limit_addr_low: {:08x}", dr.limit_addr_low);
@@ -140,34 +219,536 @@ fn show(ctx: &mut Tofino, name: &str) -> Result<()> {
     println!("tail_ptr: {:08x}", dr.tail_ptr);
     println!("ring_timeout: {:08x}", dr.ring_timeout);
     println!("data_timeout: {:08x}", dr.data_timeout);
-    println!("status: {:08x}", dr.status);
+    println!(
+        "status: {:08x} {}",
+        dr.status,
+        fields_line(&report.status_fields)
+    );
     Ok(())
 }
 
-fn dump(ctx: &mut Tofino) -> Result<()> {
+fn dump(ctx: &Tofino, format: Format) -> Result<()> {
+    let rings = rings(&ctx.map)?;
+    if format == Format::Json {
+        let reports = rings
+            .iter()
+            .map(|r| DrReport::read(ctx, r))
+            .collect::<Result<Vec<DrReport>>>()?;
+        return print_json(&reports);
+    }
+
     println!(
         "{:21} {:8} {:16} {:16} {:>6} {:>6} {:8}",
         "NAME", "CTRL", "BASE", "LIMIT", "HEAD", "TAIL", "STATUS"
     );
-    for (name, read_fn) in DR_FNS {
-        let dr = read_fn(ctx)?;
-        let base = (dr.base_addr_high as u64) << 32 | dr.base_addr_low as u64;
-        let limit =
-            (dr.limit_addr_high as u64) << 32 | dr.limit_addr_low as u64;
+    for ring in &rings {
+        let r = DrReport::read(ctx, ring)?;
         println!(
             "{:21} {:08x} {:016x} {:016x} {:>6x} {:>6x} {:08x}",
-            name, dr.ctrl, base, limit, dr.head_ptr, dr.tail_ptr, dr.status
+            r.name,
+            r.dr.ctrl,
+            r.base,
+            r.limit,
+            r.dr.head_ptr,
+            r.dr.tail_ptr,
+            r.dr.status
+        );
+        if !r.size_matches {
+            println!("base->limit range doesn't match size of {}", r.dr.size);
+        }
+    }
+    Ok(())
+}
+
+/// Descriptors are taken to be 64-bit words, which a ring would start and
+/// end on.  Nothing in tf2.rsf confirms this, so a ring that isn't aligned to
+/// them only draws a warning.
+const DR_ALIGN: u64 = 8;
+
+/// A problem found by `dr monitor`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Problem {
+    /// The ring stayed full without its tail advancing
+    Stalled,
+    /// The ring is disabled, but still holds entries
+    DisabledPending,
+    /// The base or limit isn't on a descriptor boundary.  Only ever a
+    /// warning, as described above.
+    Misaligned,
+    /// The limit is below the base
+    Backwards,
+    /// The span from base to limit disagrees with the size register
+    SizeMismatch,
+    /// The ring's memory overlaps that of the named ring
+    Overlaps(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Stalled => write!(f, "stalled"),
+            Problem::DisabledPending => write!(f, "disabled-pending"),
+            Problem::Misaligned => write!(f, "misaligned"),
+            Problem::Backwards => write!(f, "backwards"),
+            Problem::SizeMismatch => write!(f, "size-mismatch"),
+            Problem::Overlaps(other) => write!(f, "overlaps:{other}"),
+        }
+    }
+}
+
+/// An error recorded by a ring's read error log
+#[derive(Debug, Serialize)]
+struct ReadError {
+    log: String,
+    status: u64,
+    head_ptr: u64,
+}
+
+/// The JSON form of a ring in `dr monitor`.  `occupancy` is the distance
+/// from the tail to the head at the last sample, and the rates are the
+/// distances the head (producer) and tail (consumer) advanced per second.
+#[derive(Serialize)]
+struct RingHealth {
+    name: String,
+    enabled: bool,
+    size: u32,
+    occupancy: u64,
+    head_rate: f64,
+    tail_rate: f64,
+    problems: Vec<Problem>,
+    /// Possible problems, which rest on undocumented assumptions
+    warnings: Vec<Problem>,
+    read_error: Option<ReadError>,
+}
+
+/// A DMA state log, with its nonzero fields
+#[derive(Serialize)]
+struct DmaLog {
+    path: String,
+    value: u32,
+    fields: Vec<DrField>,
+}
+
+/// The JSON form of `dr monitor`.  `dma_logs` holds the DMA state of every
+/// bus with a ring that has a problem or a read error.
+#[derive(Serialize)]
+struct MonitorReport {
+    samples: u32,
+    seconds: f64,
+    rings: Vec<RingHealth>,
+    dma_logs: Vec<DmaLog>,
+}
+
+// How far a pointer advanced from `from` to `to` around a ring of `size`.
+fn advance(from: u32, to: u32, size: u32) -> u64 {
+    match size {
+        0 => 0,
+        _ => (to as i64 - from as i64).rem_euclid(size as i64) as u64,
+    }
+}
+
+// The block holding the registers shared by a ring's bus, e.g.
+// "device_select.tbc.tbc_tbus".
+fn bus_block(ring: &Ring) -> Option<String> {
+    let mut parts = ring.path.split('.');
+    let (top, bus) = (parts.next()?, parts.next()?);
+    Some(format!("{top}.{bus}.{bus}_{}bus", bus.get(..1)?))
+}
+
+// Find the register logging errors in reading a ring's descriptors, which
+// only the rings the device reads from have.  A log shared by several rings
+// is indexed by ring number, which may be part of the block's name, as in
+// "cbc_wl1_tx_dr".
+fn read_error_log(map: &RegMap, ring: &Ring) -> Option<String> {
+    let mut parts = ring.path.split('.').skip(1);
+    let (bus, block) = (parts.next()?, parts.next()?);
+    let mut index = parts.next().and_then(|i| i.parse::<u32>().ok());
+    let mut kind = block.strip_prefix(bus)?.strip_prefix('_')?.to_string();
+    if let Some((first, rest)) = kind.split_once('_') {
+        let name = first.trim_end_matches(|c: char| c.is_ascii_digit());
+        if !name.is_empty() && name.len() < first.len() {
+            index = first[name.len()..].parse().ok();
+            kind = format!("{name}_{rest}");
+        }
+    }
+
+    let log = join_path(&bus_block(ring)?, &format!("{kind}_rd_err_log"));
+    let is_reg =
+        |p: &str| map.get_node(p).is_ok_and(|n| n.register().is_some());
+    match index.map(|i| join_path(&log, &i.to_string())) {
+        Some(indexed) if is_reg(&indexed) => Some(indexed),
+        _ => is_reg(&log).then_some(log),
+    }
+}
+
+fn read_error(ctx: &Tofino, ring: &Ring) -> Result<Option<ReadError>> {
+    let Some(log) = read_error_log(&ctx.map, ring) else {
+        return Ok(None);
+    };
+    let node = ctx.map.get_node(&log)?;
+    let value = ctx.pci.read4(node.offset)? as u64;
+    let fields = &node.register().unwrap().fields;
+    let get = |suffix: &str| {
+        fields
+            .iter()
+            .find(|f| f.name.ends_with(suffix))
+            .map_or(0, |f| f.extract(value))
+    };
+    let status = get("_rd_err_status");
+    Ok((status != 0).then(|| ReadError {
+        head_ptr: get("_rd_err_head_ptr"),
+        status,
+        log,
+    }))
+}
+
+// Read the DMA state logs beneath a bus's block.
+fn dma_logs(ctx: &Tofino, block: &str) -> Result<Vec<DmaLog>> {
+    let mut logs = Vec::new();
+    ctx.map.walk(block, &mut |path, node| {
+        if path.contains("dma_log") {
+            let value = ctx.pci.read4(node.offset)?;
+            let fields = node
+                .register()
+                .unwrap()
+                .fields
+                .iter()
+                .map(|f| DrField {
+                    name: f.name.clone(),
+                    value: f.extract(value as u64),
+                })
+                .filter(|f| f.value != 0)
+                .collect();
+            logs.push(DmaLog { path: path.to_string(), value, fields });
+        }
+        Ok(())
+    })?;
+    Ok(logs)
+}
+
+// Judge the health of a ring from successive samples of its registers,
+// taken over `seconds`.
+fn analyze(
+    map: &RegMap,
+    ring: &Ring,
+    samples: &[Dr],
+    seconds: f64,
+) -> Result<RingHealth> {
+    let set = |reg: &str, value: u32, field: &str| -> Result<bool> {
+        Ok(decode(map, ring, reg, value)?
+            .iter()
+            .any(|f| f.name == field && f.value != 0))
+    };
+    let last = samples.last().ok_or(anyhow!("no samples of {}", ring.name))?;
+    let enabled = set("ctrl", last.ctrl, "en")?;
+    let size = last.size;
+    let occupancy = match set("status", last.status, "ring_full")? {
+        true => size as u64,
+        false => advance(last.tail_ptr, last.head_ptr, size),
+    };
+
+    let (mut produced, mut consumed) = (0, 0);
+    for pair in samples.windows(2) {
+        produced += advance(pair[0].head_ptr, pair[1].head_ptr, size);
+        consumed += advance(pair[0].tail_ptr, pair[1].tail_ptr, size);
+    }
+    let mut always_full = true;
+    for dr in samples {
+        always_full &= set("status", dr.status, "ring_full")?;
+    }
+    let rate = |moved: u64| match seconds > 0.0 {
+        true => moved as f64 / seconds,
+        false => 0.0,
+    };
+
+    let mut problems = Vec::new();
+    if samples.len() > 1 && always_full && consumed == 0 {
+        problems.push(Problem::Stalled);
+    }
+    if !enabled && occupancy > 0 {
+        problems.push(Problem::DisabledPending);
+    }
+    let (base, limit) = (last.base(), last.limit());
+    if limit < base {
+        problems.push(Problem::Backwards);
+    }
+    let mut warnings = Vec::new();
+    if base % DR_ALIGN != 0 || limit % DR_ALIGN != 0 {
+        warnings.push(Problem::Misaligned);
+    }
+    if !last.size_matches() {
+        problems.push(Problem::SizeMismatch);
+    }
+
+    Ok(RingHealth {
+        name: ring.name.clone(),
+        enabled,
+        size,
+        occupancy,
+        head_rate: rate(produced),
+        tail_rate: rate(consumed),
+        problems,
+        warnings,
+        read_error: None,
+    })
+}
+
+// Sample the rings `count` times, `interval` apart, and report on their
+// health.
+fn survey(
+    ctx: &Tofino,
+    rings: &[Ring],
+    count: u32,
+    interval: Duration,
+) -> Result<MonitorReport> {
+    if count == 0 {
+        bail!("at least one sample is needed");
+    }
+    let mut samples: Vec<Vec<Dr>> = rings.iter().map(|_| Vec::new()).collect();
+    let start = Instant::now();
+    for i in 0..count {
+        if i > 0 {
+            std::thread::sleep(interval);
+        }
+        for (ring, s) in rings.iter().zip(samples.iter_mut()) {
+            s.push(read_dr(ctx, ring)?);
+        }
+    }
+    let seconds = start.elapsed().as_secs_f64();
+
+    let mut health = Vec::new();
+    for (ring, s) in rings.iter().zip(&samples) {
+        let mut h = analyze(&ctx.map, ring, s, seconds)?;
+        h.read_error = read_error(ctx, ring)?;
+        health.push(h);
+    }
+
+    // Rings with no memory assigned can't overlap anything.
+    let spans: Vec<(u64, u64)> = samples
+        .iter()
+        .map(|s| s.last().map_or((0, 0), |dr| (dr.base(), dr.limit())))
+        .collect();
+    for (i, a) in spans.iter().enumerate() {
+        for (j, b) in spans.iter().enumerate().skip(i + 1) {
+            if a.0 < a.1 && b.0 < b.1 && a.0 < b.1 && b.0 < a.1 {
+                let other = health[j].name.clone();
+                health[i].problems.push(Problem::Overlaps(other));
+                let other = health[i].name.clone();
+                health[j].problems.push(Problem::Overlaps(other));
+            }
+        }
+    }
+
+    let mut blocks = BTreeSet::new();
+    for (ring, h) in rings.iter().zip(&health) {
+        if !h.problems.is_empty() || h.read_error.is_some() {
+            blocks.extend(bus_block(ring));
+        }
+    }
+    let mut logs = Vec::new();
+    for block in blocks {
+        logs.extend(dma_logs(ctx, &block)?);
+    }
+
+    Ok(MonitorReport { samples: count, seconds, rings: health, dma_logs: logs })
+}
+
+fn monitor(
+    ctx: &Tofino,
+    names: &[String],
+    count: u32,
+    interval: u64,
+    format: Format,
+) -> Result<()> {
+    let all = rings(&ctx.map)?;
+    let rings = match names.is_empty() {
+        true => all,
+        false => names
+            .iter()
+            .map(|n| find_ring(&all, n))
+            .collect::<Result<Vec<Ring>>>()?,
+    };
+    let report = survey(ctx, &rings, count, Duration::from_millis(interval))?;
+    if format == Format::Json {
+        return print_json(&report);
+    }
+
+    println!(
+        "{:21} {:3} {:>8} {:>8} {:>10} {:>10} PROBLEMS",
+        "NAME", "EN", "SIZE", "OCC", "HEAD/S", "TAIL/S"
+    );
+    for h in &report.rings {
+        let problems = match h.problems.is_empty() {
+            true => "-".to_string(),
+            false => h
+                .problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+        };
+        println!(
+            "{:21} {:3} {:>8x} {:>8x} {:>10.0} {:>10.0} {problems}",
+            h.name,
+            if h.enabled { "yes" } else { "no" },
+            h.size,
+            h.occupancy,
+            h.head_rate,
+            h.tail_rate,
         );
-        if limit - base != dr.size as u64 {
-            println!("base->limit range doesn't match size of {}", dr.size);
+    }
+    for h in &report.rings {
+        for w in &h.warnings {
+            println!("{}: warning: {w}", h.name);
         }
     }
+    for h in &report.rings {
+        if let Some(e) = &h.read_error {
+            println!(
+                "{}: read error {:#x} at head {:#x} ({})",
+                h.name, e.status, e.head_ptr, e.log
+            );
+        }
+    }
+    for log in &report.dma_logs {
+        println!(
+            "{}: {:08x} {}",
+            log.path,
+            log.value,
+            fields_line(&log.fields)
+        );
+    }
     Ok(())
 }
 
-pub fn dr_command(ctx: &mut Tofino, cmd: DrCommands) -> Result<()> {
+pub fn dr_command(ctx: &Tofino, cmd: DrCommands, format: Format) -> Result<()> {
     match cmd {
-        DrCommands::Show { dr } => show(ctx, &dr),
-        DrCommands::Dump => dump(ctx),
+        DrCommands::Show { dr } => show(ctx, &dr, format),
+        DrCommands::Dump => dump(ctx, format),
+        DrCommands::Monitor { rings, interval, count } => {
+            monitor(ctx, &rings, count, interval, format)
+        }
     }
 }
+
+#[test]
+fn test_dr_rings() {
+    use tofino::sim::SimAsic;
+
+    let map = RegMap::new().unwrap();
+    let all = rings(&map).unwrap();
+    assert_eq!(all.len(), 56);
+    for name in [
+        "tbus_fm_7",
+        "pbc_il_cpl_3",
+        "pbc_diag_rx",
+        "cbc_lq_fm",
+        "mbc_mac_0_tx",
+    ] {
+        assert!(all.iter().any(|r| r.name == name), "missing {name}");
+    }
+    let ring = find_ring(&all, "tbc_rx_2").unwrap();
+    assert_eq!(ring.name, "tbus_rx_2");
+    assert_eq!(ring.path, "device_select.tbc.tbc_rx_dr.2");
+
+    let ring = find_ring(&all, "cbc_wl1_tx").unwrap();
+    let sim = SimAsic::new();
+    sim.set(map.get_offset(&join_path(&ring.path, "ctrl")).unwrap(), 0x5);
+    sim.set(map.get_offset(&join_path(&ring.path, "status")).unwrap(), 0xa);
+    let ctx = Tofino { map, pci: Box::new(sim) };
+    let report = DrReport::read(&ctx, &ring).unwrap();
+    assert_eq!(
+        fields_line(&report.ctrl_fields),
+        "en=0x1 write_time_mode=0x0 head_ptr_mode=0x1 tail_ptr_mode=0x0"
+    );
+    assert_eq!(
+        fields_line(&report.status_fields),
+        "ring_empty=0x0 ring_full=0x1 queue_empty=0x0 queue_full=0x1"
+    );
+}
+
+#[test]
+fn test_dr_monitor() {
+    use tofino::sim::SimAsic;
+
+    let map = RegMap::new().unwrap();
+    let all = rings(&map).unwrap();
+    let log =
+        |name: &str| read_error_log(&map, &find_ring(&all, name).unwrap());
+    assert_eq!(
+        log("tbc_tx_2").as_deref(),
+        Some("device_select.tbc.tbc_tbus.tx_dr_rd_err_log.2")
+    );
+    assert_eq!(
+        log("cbc_wl1_tx").as_deref(),
+        Some("device_select.cbc.cbc_cbus.wl_tx_dr_rd_err_log.1")
+    );
+    assert_eq!(
+        log("mbc_mac_0_tx").as_deref(),
+        Some("device_select.mbc.mbc_mbus.mac_0_tx_dr_rd_err_log")
+    );
+    assert_eq!(log("tbc_cpl_0"), None);
+
+    let sim = SimAsic::new();
+    let offset = |ring: &Ring, reg: &str| {
+        map.get_offset(&join_path(&ring.path, reg)).unwrap()
+    };
+    let setup = |name: &str, ctrl, base, size, head, tail, status| {
+        let ring = find_ring(&all, name).unwrap();
+        sim.set(offset(&ring, "ctrl"), ctrl);
+        sim.set(offset(&ring, "base_addr_low"), base);
+        sim.set(offset(&ring, "limit_addr_low"), base + size);
+        sim.set(offset(&ring, "size"), size);
+        sim.set(offset(&ring, "head_ptr"), head);
+        sim.set(offset(&ring, "tail_ptr"), tail);
+        sim.set(offset(&ring, "status"), status);
+        ring
+    };
+    // A full ring whose consumer has stopped, and which has logged an error
+    let stalled = setup("tbc_tx_0", 1, 0x1000, 0x100, 0x40, 0x40, 0x2);
+    sim.set(map.get_offset(&log("tbc_tx_0").unwrap()).unwrap(), 0x40 << 3 | 1);
+    sim.set(map.get_offset("device_select.tbc.tbc_tbus.dma_log").unwrap(), 0x2);
+    // A disabled ring with entries, sharing memory with the stalled ring
+    let disabled = setup("tbc_tx_1", 0, 0x1080, 0x100, 0x20, 0, 0);
+    let misaligned = setup("tbc_fm_0", 1, 0x2004, 0x100, 0, 0, 0x1);
+    // A healthy ring, whose producer adds an entry before every sample
+    let busy = setup("cbc_wl1_tx", 1, 0x3000, 0x100, 0xf8, 0xf0, 0);
+    sim.on_read(offset(&busy, "head_ptr"), |regs, off| {
+        let head = (regs.get(off) + 8) % 0x100;
+        regs.set(off, head);
+        head
+    });
+
+    let ctx = Tofino { map, pci: Box::new(sim) };
+    let rings = [stalled, disabled, misaligned, busy];
+    let report = survey(&ctx, &rings, 4, Duration::ZERO).unwrap();
+    let problems: Vec<&[Problem]> =
+        report.rings.iter().map(|h| h.problems.as_slice()).collect();
+    assert_eq!(
+        problems,
+        [
+            &[Problem::Stalled, Problem::Overlaps("tbus_tx_1".into())][..],
+            &[Problem::DisabledPending, Problem::Overlaps("tbus_tx_0".into())],
+            &[],
+            &[],
+        ]
+    );
+    assert_eq!(report.rings[2].warnings, [Problem::Misaligned]);
+    assert_eq!(report.rings[0].occupancy, 0x100);
+    assert_eq!(report.rings[1].occupancy, 0x20);
+    let busy = &report.rings[3];
+    assert_eq!(busy.occupancy, 0x28);
+    assert!(busy.head_rate > 0.0 && busy.tail_rate == 0.0);
+
+    let err = report.rings[0].read_error.as_ref().unwrap();
+    assert_eq!((err.status, err.head_ptr), (1, 0x40));
+    assert!(report.rings[1].read_error.is_none());
+    let paths: Vec<&str> =
+        report.dma_logs.iter().map(|l| l.path.as_str()).collect();
+    assert_eq!(paths, ["device_select.tbc.tbc_tbus.dma_log"]);
+    assert_eq!(
+        fields_line(&report.dma_logs[0].fields),
+        "tx_write_statemachine=0x1"
+    );
+}
@@ -0,0 +1,377 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{Context, Result, anyhow, bail};
+use regs::map::{Field, Register};
+use serde::Serialize;
+
+use crate::intr::{clear_register, is_status, names, read_value};
+use crate::{Format, Tofino, print_json, reachable};
+
+/// The kind of error recorded by a log register
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Sbe,
+    Mbe,
+    Parity,
+}
+
+impl Kind {
+    fn parse(token: &str) -> Option<Kind> {
+        match token {
+            "sbe" | "serr" => Some(Kind::Sbe),
+            "mbe" | "merr" => Some(Kind::Mbe),
+            "par" | "parity" => Some(Kind::Parity),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Kind::Sbe => "sbe",
+            Kind::Mbe => "mbe",
+            Kind::Parity => "parity",
+        };
+        write!(f, "{s}")
+    }
+}
+
+// Reduce a register or field name to the form used to match an error log with
+// its bit in an interrupt status register: the status bits use "serr" and
+// "merr" as often as "sbe" and "mbe", and separate words inconsistently.
+fn match_key<'a>(tokens: impl Iterator<Item = &'a str>) -> String {
+    tokens
+        .map(|t| Kind::parse(t).map_or(t.to_string(), |k| k.to_string()))
+        .collect()
+}
+
+/// The name of an error log, broken into its parts
+#[derive(Debug, PartialEq)]
+struct LogName {
+    // The memory the log covers, which may be empty
+    memory: String,
+    kind: Kind,
+    // The name as reduced by `match_key`
+    key: String,
+}
+
+// Decide whether a register is an error log from its name, which looks like
+// "linkmem_sbe_err_log", "tcam_sbe_errlog" or "enfifo_serr_ep0_log".  Logs
+// too wide for one register are split into words named "<log>_<n>_<count>".
+fn classify(name: &str) -> Option<LogName> {
+    let mut tokens: Vec<&str> = name.split('_').collect();
+    while tokens.last().is_some_and(|t| t.parse::<u32>().is_ok()) {
+        tokens.pop();
+    }
+    if !matches!(tokens.last(), Some(&"log" | &"errlog")) {
+        return None;
+    }
+    while matches!(tokens.last(), Some(&"log" | &"errlog" | &"err")) {
+        tokens.pop();
+    }
+    let (pos, kind) = tokens
+        .iter()
+        .enumerate()
+        .find_map(|(i, t)| Some((i, Kind::parse(t)?)))?;
+    Some(LogName {
+        memory: tokens[..pos].join("_"),
+        kind,
+        key: match_key(tokens.iter().copied()),
+    })
+}
+
+// Strip the register name, and any array index, from a path.
+fn parent_block(path: &str) -> &str {
+    let mut rest = path;
+    while let Some((parent, leaf)) = rest.rsplit_once('.') {
+        rest = parent;
+        if leaf.parse::<u32>().is_err() {
+            return rest;
+        }
+    }
+    ""
+}
+
+#[derive(Serialize)]
+struct LogField {
+    name: String,
+    value: u64,
+}
+
+/// An error log that has recorded an error, in the JSON form of `ecc`.  The
+/// logs hold the address of the most recent error, so a log that has caught
+/// an error at address 0 is only known to have done so through the matching
+/// bit of the interrupt status register in the same block.  `asserted` holds
+/// that bit, and is null if no such bit could be found.
+#[derive(Serialize)]
+struct ErrorLog {
+    path: String,
+    offset: u32,
+    memory: String,
+    kind: Kind,
+    value: u64,
+    asserted: Option<bool>,
+    fields: Vec<LogField>,
+    // The path of the status register and the name of the matching field
+    #[serde(skip)]
+    status: Option<(String, String)>,
+}
+
+/// The JSON form of `ecc`
+#[derive(Serialize)]
+struct EccReport {
+    scanned: usize,
+    reported: Vec<ErrorLog>,
+    check_disabled: Vec<String>,
+}
+
+// Find the bit reporting errors for a log among the status registers beneath
+// `block`.  A bit whose name is the log's is preferred over one whose name
+// merely ends with it.
+fn find_status_bit<'a>(
+    ctx: &'a Tofino,
+    statuses: &'a [(String, u32)],
+    block: &str,
+    log: &LogName,
+) -> Result<Option<(&'a str, u32, &'a Register, &'a Field)>> {
+    let mut candidates = Vec::new();
+    for (path, offset) in statuses {
+        if block.is_empty() || path.starts_with(&format!("{block}.")) {
+            let reg = ctx.map.get_node(path)?.register().unwrap();
+            candidates.push((path.as_str(), *offset, reg));
+        }
+    }
+    let partial = !log.memory.is_empty();
+    for exact in [true, false] {
+        for &(path, offset, reg) in &candidates {
+            let found = reg.fields.iter().find(|f| {
+                let k = match_key(f.name.split('_'));
+                k == log.key || (!exact && partial && k.ends_with(&log.key))
+            });
+            if let Some(f) = found {
+                return Ok(Some((path, offset, reg, f)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn survey(ctx: &Tofino, root: &str) -> Result<EccReport> {
+    let mut logs = Vec::new();
+    let mut statuses = Vec::new();
+    let mut controls = Vec::new();
+    ctx.map.walk(root, &mut |path, node| {
+        if !reachable(node) {
+            return Ok(());
+        }
+        let reg = node.register().unwrap();
+        let (name, _) = names(path);
+        if let Some(log) = classify(name) {
+            logs.push((path.to_string(), node.offset, log));
+        } else if is_status(path, reg) {
+            statuses.push((path.to_string(), node.offset));
+        } else if name == "ecc" || name.ends_with("_ecc") {
+            controls.push((path.to_string(), node.offset));
+        }
+        Ok(())
+    })?;
+
+    // Several logs usually share a status register, so read each just once.
+    let mut status_values = HashMap::new();
+    let mut reported = Vec::new();
+    for (path, offset, log) in &logs {
+        let reg = ctx.map.get_node(path)?.register().unwrap();
+        let value = read_value(ctx, *offset, reg)
+            .with_context(|| format!("reading {path}"))?;
+        let block = parent_block(path);
+        let (asserted, status) =
+            match find_status_bit(ctx, &statuses, block, log)? {
+                Some((status_path, offset, status_reg, field)) => {
+                    let status_value = match status_values.get(&offset) {
+                        Some(v) => *v,
+                        None => {
+                            let v = read_value(ctx, offset, status_reg)?;
+                            status_values.insert(offset, v);
+                            v
+                        }
+                    };
+                    let asserted = field.extract(status_value) != 0;
+                    let status = (status_path.to_string(), field.name.clone());
+                    (Some(asserted), Some(status))
+                }
+                None => (None, None),
+            };
+        if value == 0 && asserted != Some(true) {
+            continue;
+        }
+
+        let memory = match log.memory.is_empty() {
+            true => names(block).0.to_string(),
+            false => log.memory.clone(),
+        };
+        let fields = reg
+            .fields
+            .iter()
+            .filter(|f| f.mode.is_readable())
+            .map(|f| LogField { name: f.name.clone(), value: f.extract(value) })
+            .collect();
+        reported.push(ErrorLog {
+            path: path.clone(),
+            offset: *offset,
+            memory,
+            kind: log.kind,
+            value,
+            asserted,
+            fields,
+            status,
+        });
+    }
+
+    // A memory with checking disabled will never log anything, which would
+    // otherwise look like a clean bill of health.
+    let mut check_disabled = Vec::new();
+    for (path, offset) in &controls {
+        let reg = ctx.map.get_node(path)?.register().unwrap();
+        let value = read_value(ctx, *offset, reg)
+            .with_context(|| format!("reading {path}"))?;
+        for f in &reg.fields {
+            if f.name.ends_with("disable_check") && f.extract(value) != 0 {
+                check_disabled.push(format!("{path}.{}", f.name));
+            }
+        }
+    }
+
+    Ok(EccReport { scanned: logs.len(), reported, check_disabled })
+}
+
+// Zero a log, where software is able to, and clear its interrupt status bit
+// so that the next error is latched.
+fn reset_log(ctx: &Tofino, log: &ErrorLog) -> Result<()> {
+    let reg = ctx.map.get_node(&log.path)?.register().unwrap();
+    if reg.fields.iter().any(|f| f.mode.is_writable()) {
+        for word in 0..reg.width.div_ceil(32) {
+            ctx.pci.write4(log.offset + 4 * word, 0)?;
+        }
+    }
+    if let Some((path, field)) = &log.status {
+        let node = ctx.map.get_node(path)?;
+        let status = node.register().unwrap();
+        let f = status
+            .fields
+            .iter()
+            .find(|f| &f.name == field)
+            .ok_or(anyhow!("{path} has no field {field}"))?;
+        clear_register(ctx, node.offset, status, &[f])?;
+    }
+    Ok(())
+}
+
+/// Report every memory whose single-bit, multi-bit or parity error log has
+/// recorded an error, optionally resetting the logs afterwards.
+pub fn ecc(
+    ctx: &Tofino,
+    root: &str,
+    reset: bool,
+    format: Format,
+) -> Result<()> {
+    let report = survey(ctx, root)?;
+    if report.scanned == 0 {
+        bail!("no error logs found at {root}");
+    }
+
+    match format {
+        Format::Json => print_json(&report)?,
+        Format::Text => {
+            for log in &report.reported {
+                let fields = log
+                    .fields
+                    .iter()
+                    .map(|f| format!("{}={:#x}", f.name, f.value))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                println!(
+                    "{:<6} {:24} {} @ {:#x}: {fields}",
+                    log.kind, log.memory, log.path, log.offset
+                );
+            }
+            println!(
+                "{} of {} error logs have recorded errors",
+                report.reported.len(),
+                report.scanned
+            );
+            for c in &report.check_disabled {
+                println!("checking disabled: {c}");
+            }
+        }
+    }
+
+    if reset {
+        for log in &report.reported {
+            reset_log(ctx, log)
+                .with_context(|| format!("resetting {}", log.path))?;
+        }
+        if format == Format::Text && !report.reported.is_empty() {
+            println!("reset {} error logs", report.reported.len());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_ecc_logs() {
+    use tofino::sim::SimAsic;
+
+    let log = classify("enfifo_serr_ep0_log").unwrap();
+    assert_eq!((log.memory.as_str(), log.kind), ("enfifo", Kind::Sbe));
+    assert_eq!(log.key, match_key("enfifo_serr_ep_0".split('_')));
+    let log = classify("cache_mbe_err_log_1_2").unwrap();
+    assert_eq!((log.memory.as_str(), log.key.as_str()), ("cache", "cachembe"));
+    assert_eq!(classify("sbe_log").unwrap().memory, "");
+    assert_eq!(classify("pex_credit_err_log"), None);
+    assert_eq!(classify("tcam_logical_channel_errlog_lo"), None);
+
+    let pex = "device_select.tm_top.tm_pex_top.pex.2";
+    let map = regs::map::RegMap::new().unwrap();
+    let log = map.get_offset(&format!("{pex}.dq_ph_fifo_sbe_err_log")).unwrap();
+    let stat = map.get_offset(&format!("{pex}.intr.stat")).unwrap();
+    let sim = SimAsic::new();
+    // An error at address 0 is only visible through the status bit.
+    sim.set(stat, 0b100);
+    // The status bits are write-1-to-clear
+    sim.on_write(stat, |regs, offset, val| {
+        regs.set(offset, regs.get(offset) & !val)
+    });
+    let ctx = Tofino { map, pci: Box::new(sim) };
+
+    let report = survey(&ctx, "device_select.tm_top.tm_pex_top").unwrap();
+    assert_eq!(report.reported.len(), 1);
+    let reported = &report.reported[0];
+    assert_eq!(reported.offset, log);
+    assert_eq!((reported.kind, reported.asserted), (Kind::Sbe, Some(true)));
+
+    ecc(&ctx, pex, true, Format::Json).unwrap();
+    assert!(survey(&ctx, pex).unwrap().reported.is_empty());
+
+    // Resetting one log must leave another's status bit, in the same
+    // register, asserted.
+    let sim = SimAsic::new();
+    sim.set(stat, 0b1100);
+    sim.on_write(stat, |regs, offset, val| {
+        regs.set(offset, regs.get(offset) & !val)
+    });
+    let ctx = Tofino { map: ctx.map, pci: Box::new(sim) };
+    let report = survey(&ctx, pex).unwrap();
+    assert_eq!(report.reported.len(), 2);
+    let sbe = report.reported.iter().find(|r| r.kind == Kind::Sbe).unwrap();
+    reset_log(&ctx, sbe).unwrap();
+    assert_eq!(ctx.pci.read4(stat).unwrap(), 0b1000);
+}
@@ -4,9 +4,12 @@
 
 // Copyright 2023 Oxide Computer Company
 
-use anyhow::Result;
+use std::path::Path;
 
-use crate::Tofino;
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{Format, Tofino, print_json};
 use tofino::fuse;
 
 macro_rules! print_field {
@@ -15,9 +18,134 @@ macro_rules! print_field {
     };
 }
 
-pub fn dump_fuse(ctx: &mut Tofino) -> Result<()> {
-    let fuse = fuse::Fuse::read(&ctx.pci)?;
+/// The JSON form of `fuse`: every decoded fuse field, plus a `wafer` object
+/// holding the fab, lot, wafer and die location parsed from `chip_id`.
+#[derive(Serialize)]
+struct FuseReport<'a> {
+    #[serde(flatten)]
+    fuse: &'a fuse::Fuse,
+    wafer: fuse::ChipId,
+}
+
+/// The JSON form of `fuse --raw`: the fuse words in register order.
+#[derive(Serialize)]
+struct RawReport<'a> {
+    words: &'a [u32],
+}
+
+pub fn dump_fuse(
+    ctx: &mut Tofino,
+    restrictions: bool,
+    format: Format,
+) -> Result<()> {
+    let fuse = fuse::Fuse::read(ctx.pci.as_ref())?;
+    show(&fuse, restrictions, format)
+}
+
+/// Print the raw fuse words, and optionally save them for later analysis.
+/// The saved file is always in the text dump format.
+pub fn dump_raw(
+    ctx: &mut Tofino,
+    save: Option<&Path>,
+    format: Format,
+) -> Result<()> {
+    let words = fuse::read_raw(ctx.pci.as_ref())?;
+    let dump = fuse::format_dump(&words);
+    match format {
+        Format::Json => print_json(&RawReport { words: &words })?,
+        Format::Text => print!("{dump}"),
+    }
+    if let Some(path) = save {
+        std::fs::write(path, dump)
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Decode fuse data saved from another system.
+pub fn dump_file(
+    path: &Path,
+    binary: bool,
+    restrictions: bool,
+    format: Format,
+) -> Result<()> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("reading {}", path.display()))?;
+    let dump_format = match binary {
+        true => fuse::DumpFormat::Binary,
+        false => fuse::DumpFormat::Text,
+    };
+    let words = fuse::parse_dump(&data, dump_format)
+        .with_context(|| format!("parsing {}", path.display()))?;
+    let fuse = fuse::Fuse::try_from_slice(&words)?;
+    show(&fuse, restrictions, format)
+}
+
+// The JSON form of `fuse --restrictions` is `tofino::fuse::Restrictions`.
+fn show(fuse: &fuse::Fuse, restrictions: bool, format: Format) -> Result<()> {
+    match (format, restrictions) {
+        (Format::Json, true) => {
+            print_json(&fuse::Restrictions::from(fuse))?;
+        }
+        (Format::Json, false) => {
+            print_json(&FuseReport { fuse, wafer: fuse.chip_id.into() })?
+        }
+        (Format::Text, true) => print_restrictions(fuse),
+        (Format::Text, false) => print_fuse(fuse),
+    }
+    Ok(())
+}
+
+fn print_restrictions(fuse: &fuse::Fuse) {
+    let r = fuse::Restrictions::from(fuse);
+    let bits = |bits: &[u32]| match bits.is_empty() {
+        true => "none".to_string(),
+        false => bits
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<String>>()
+            .join(" "),
+    };
 
+    println!("pipes:");
+    for p in &r.pipes {
+        let state = match (p.disabled, p.soft_disabled) {
+            (true, _) => "disabled",
+            (false, true) => "disabled in software",
+            (false, false) => "enabled",
+        };
+        println!(
+            "  pipe {}: {}, mau_dis bits: {}",
+            p.pipe,
+            state,
+            bits(&p.mau_dis)
+        );
+    }
+    println!("port_dis bits: {}", bits(&r.port_dis));
+    println!("speed_dis: {:#x}", r.speed_dis);
+    println!("cpu_speed_dis: {:#x}", r.cpu_speed_dis);
+    println!(
+        "core clock limit: freq_bps {:#x}, freq_bps_2 {:#x}",
+        r.core_freq[0], r.core_freq[1]
+    );
+    println!(
+        "pps clock limit: freq_pps {:#x}, freq_pps_2 {:#x}",
+        r.pps_freq[0], r.pps_freq[1]
+    );
+    if r.freq_limits_disabled {
+        println!("frequency limits: disabled by freq_dis");
+    }
+    println!("tm_mem_dis bits: {}", bits(&r.tm_mem_dis));
+    println!(
+        "features disabled: {}",
+        match r.features_disabled.is_empty() {
+            true => "none".to_string(),
+            false => r.features_disabled.join(", "),
+        }
+    );
+}
+
+fn print_fuse(fuse: &fuse::Fuse) {
     print_field!(fuse, device_id);
     print_field!(fuse, version);
     print_field!(fuse, freq_dis);
@@ -54,5 +182,4 @@ pub fn dump_fuse(ctx: &mut Tofino) -> Result<()> {
 
     let chip_id: fuse::ChipId = fuse.chip_id.into();
     println!("{:24}: {}", "wafer id", chip_id);
-    Ok(())
 }
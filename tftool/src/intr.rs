@@ -0,0 +1,335 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use anyhow::{Context, Result, anyhow, bail};
+use regs::map::{Field, Node, Register};
+use serde::Serialize;
+
+use crate::{Format, IntrCommands, Tofino, print_json, reachable};
+
+// The names given to the registers that accompany an interrupt status
+// register.  Each is found by substituting one of the alternatives for the
+// "stat" or "status" in the status register's name.
+const EN0_NAMES: [&str; 3] = ["en0", "en_0", "enable0"];
+const EN1_NAMES: [&str; 3] = ["en1", "en_1", "enable1"];
+const FREEZE_NAMES: [&str; 2] = ["freeze_en", "freeze_enable"];
+
+// Strip any array indices from a path, returning the name of the register
+// and of the block containing it.
+pub(crate) fn names(path: &str) -> (&str, &str) {
+    let mut names = path.rsplit('.').filter(|c| c.parse::<u32>().is_err());
+    (names.next().unwrap_or(""), names.next().unwrap_or(""))
+}
+
+// Decide whether a register holds interrupt status bits.
+pub(crate) fn is_status(path: &str, reg: &Register) -> bool {
+    let (name, parent) = names(path);
+    reg.doc.contains("Interrupt Status")
+        || (name == "stat" && parent.contains("intr"))
+        || ["intr_stat", "int_stat", "intr_status"]
+            .iter()
+            .any(|s| name.contains(s))
+}
+
+// Candidate names for a companion of the status register `name`, in order of
+// preference.
+fn companion_names(name: &str, alternatives: &[&str]) -> Vec<String> {
+    let Some((idx, token)) = ["status", "stat"]
+        .iter()
+        .find_map(|t| name.find(t).map(|idx| (idx, *t)))
+    else {
+        return Vec::new();
+    };
+    let (prefix, suffix) = (&name[..idx], &name[idx + token.len()..]);
+
+    let mut names = Vec::new();
+    for alt in alternatives {
+        names.push(format!("{prefix}{alt}{suffix}"));
+        // "intr_stat0" is paired with "intr_en0_0"
+        if !suffix.is_empty() && !suffix.starts_with('_') {
+            names.push(format!("{prefix}{alt}_{suffix}"));
+        }
+    }
+    names.extend(alternatives.iter().map(|a| a.to_string()));
+    names
+}
+
+// Find the offset of a companion register, which lives alongside the status
+// register in the same block.
+fn find_companion(
+    ctx: &Tofino,
+    path: &str,
+    alternatives: &[&str],
+) -> Option<u32> {
+    let (name, _) = names(path);
+    let (parent, leaf) = path.rsplit_once('.')?;
+    // If the status register is an array entry, the companion has the same
+    // index.
+    let (parent, index) = match leaf.parse::<u32>() {
+        Ok(_) => (parent.rsplit_once('.')?.0, Some(leaf)),
+        Err(_) => (parent, None),
+    };
+    companion_names(name, alternatives).iter().find_map(|c| {
+        let sibling = match index {
+            Some(i) => format!("{parent}.{c}.{i}"),
+            None => format!("{parent}.{c}"),
+        };
+        ctx.map
+            .get_node(&sibling)
+            .ok()
+            .and_then(|n| n.register().map(|_| n.offset))
+    })
+}
+
+/// An asserted field in an interrupt status register.  `en0`, `en1` and
+/// `freeze` hold the matching bits of the enable and freeze registers, and
+/// are null if the register has no such companion.
+#[derive(Serialize)]
+struct IntrField {
+    name: String,
+    msb: u32,
+    lsb: u32,
+    value: u64,
+    en0: Option<u64>,
+    en1: Option<u64>,
+    freeze: Option<u64>,
+}
+
+/// The JSON form of `intr show` is an array of these, one for each status
+/// register with a bit set.
+#[derive(Serialize)]
+struct IntrStatus {
+    path: String,
+    offset: u32,
+    value: u64,
+    fields: Vec<IntrField>,
+}
+
+// Call `f` for every interrupt status register at or beneath `path`.
+fn for_each_status<F>(ctx: &Tofino, path: &str, mut f: F) -> Result<()>
+where
+    F: FnMut(&str, &Node, &Register) -> Result<()>,
+{
+    ctx.map.walk(path, &mut |path, node| {
+        let reg = node.register().unwrap();
+        if !reachable(node) || !is_status(path, reg) {
+            return Ok(());
+        }
+        f(path, node, reg)
+    })
+}
+
+pub(crate) fn read_value(
+    ctx: &Tofino,
+    offset: u32,
+    reg: &Register,
+) -> Result<u64> {
+    let words = crate::read_offset(ctx, offset, reg.width.div_ceil(32))?;
+    reg.value(&words)
+}
+
+fn survey(ctx: &Tofino, path: &str) -> Result<Vec<IntrStatus>> {
+    let mut asserted = Vec::new();
+    for_each_status(ctx, path, |path, node, reg| {
+        let value = read_value(ctx, node.offset, reg)
+            .with_context(|| format!("reading {path}"))?;
+        if value == 0 {
+            return Ok(());
+        }
+
+        let companion = |alternatives: &[&str]| -> Result<Option<u64>> {
+            find_companion(ctx, path, alternatives)
+                .map(|offset| read_value(ctx, offset, reg))
+                .transpose()
+        };
+        let (en0, en1, freeze) = (
+            companion(&EN0_NAMES)?,
+            companion(&EN1_NAMES)?,
+            companion(&FREEZE_NAMES)?,
+        );
+        let fields = reg
+            .fields
+            .iter()
+            .filter(|f| f.mode.is_readable() && f.extract(value) != 0)
+            .map(|f| IntrField {
+                name: f.name.clone(),
+                msb: f.msb(),
+                lsb: f.lsb,
+                value: f.extract(value),
+                en0: en0.map(|v| f.extract(v)),
+                en1: en1.map(|v| f.extract(v)),
+                freeze: freeze.map(|v| f.extract(v)),
+            })
+            .collect();
+        asserted.push(IntrStatus {
+            path: path.to_string(),
+            offset: node.offset,
+            value,
+            fields,
+        });
+        Ok(())
+    })?;
+    Ok(asserted)
+}
+
+fn show(ctx: &Tofino, path: &str, format: Format) -> Result<()> {
+    let asserted = survey(ctx, path)?;
+    if format == Format::Json {
+        return print_json(&asserted);
+    }
+
+    let opt = |v: Option<u64>| v.map_or("-".to_string(), |v| format!("{v:x}"));
+    for s in &asserted {
+        println!("{} @ {:#x}: {:#x}", s.path, s.offset, s.value);
+        for f in &s.fields {
+            let bits = match f.msb == f.lsb {
+                true => format!("{}", f.lsb),
+                false => format!("{}:{}", f.msb, f.lsb),
+            };
+            println!(
+                "    {:40} {:>5} {:>8x}  en0 {:>3} en1 {:>3} freeze {:>3}",
+                f.name,
+                bits,
+                f.value,
+                opt(f.en0),
+                opt(f.en1),
+                opt(f.freeze)
+            );
+        }
+    }
+    if asserted.is_empty() {
+        println!("no interrupts asserted");
+    }
+    Ok(())
+}
+
+// Clear the given fields of an interrupt status register.  Status bits are
+// write-1-to-clear, whether or not the register map records them as such, so
+// the fields are cleared by writing 1s to them and 0s everywhere else.  No
+// other status bit is ever written back.  Returns the bits of the fields
+// still set afterwards.
+pub(crate) fn clear_register(
+    ctx: &Tofino,
+    offset: u32,
+    reg: &Register,
+    fields: &[&Field],
+) -> Result<u64> {
+    let mask = reg.clear_value(fields, true)?;
+    for word in 0..reg.width.div_ceil(32) {
+        ctx.pci.write4(offset + 4 * word, (mask >> (32 * word)) as u32)?;
+    }
+    Ok(read_value(ctx, offset, reg)? & mask)
+}
+
+/// The JSON form of `intr clear` is an array of these: the status registers
+/// with bits that were still set after being cleared, usually because the
+/// condition behind them persists.
+#[derive(Serialize)]
+struct Reasserted {
+    path: String,
+    bits: u64,
+}
+
+fn clear(
+    ctx: &Tofino,
+    root: &str,
+    field_names: &[String],
+    format: Format,
+) -> Result<()> {
+    let mut cleared = 0;
+    let mut stuck = Vec::new();
+    for_each_status(ctx, root, |path, node, reg| {
+        let fields = match field_names.is_empty() {
+            true => reg.fields.iter().collect::<Vec<&Field>>(),
+            false => field_names
+                .iter()
+                .map(|n| {
+                    reg.fields
+                        .iter()
+                        .find(|f| &f.name == n)
+                        .ok_or(anyhow!("{path} has no field {n}"))
+                })
+                .collect::<Result<Vec<&Field>>>()?,
+        };
+        // Summary registers, which can't be written, are skipped when clearing
+        // a whole block.
+        if fields.iter().any(|f| !f.mode.is_writable()) {
+            match path == root {
+                true => bail!("{path} cannot be cleared by software"),
+                false => return Ok(()),
+            }
+        }
+        let remaining = clear_register(ctx, node.offset, reg, &fields)
+            .with_context(|| format!("clearing {path}"))?;
+        cleared += 1;
+        if remaining != 0 {
+            stuck.push(Reasserted { path: path.to_string(), bits: remaining });
+        }
+        Ok(())
+    })?;
+    if cleared == 0 {
+        bail!("no interrupt status registers found at {root}");
+    }
+
+    match format {
+        Format::Json => print_json(&stuck)?,
+        Format::Text => {
+            for r in &stuck {
+                println!("{}: {:#x} reasserted after clearing", r.path, r.bits);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn intr_command(
+    ctx: &Tofino,
+    cmd: IntrCommands,
+    format: Format,
+) -> Result<()> {
+    match cmd {
+        IntrCommands::Show { path } => show(ctx, &path, format),
+        IntrCommands::Clear { path, fields } => {
+            clear(ctx, &path, &fields, format)
+        }
+    }
+}
+
+#[test]
+fn test_intr_clear() {
+    use tofino::sim::SimAsic;
+
+    assert_eq!(
+        companion_names("intr_stat0", &EN0_NAMES)[..2],
+        ["intr_en00".to_string(), "intr_en0_0".to_string()]
+    );
+
+    let map = regs::map::RegMap::new().unwrap();
+    let stat = map.get_offset("device_select.lfltr.1.ctrl.intr_stat").unwrap();
+    let en0 = map.get_offset("device_select.lfltr.1.ctrl.intr_en0").unwrap();
+    let sim = SimAsic::new();
+    sim.set(stat, 0b1010);
+    sim.set(en0, 0b0010);
+    // The status bits are write-1-to-clear
+    sim.on_write(stat, |regs, offset, val| {
+        regs.set(offset, regs.get(offset) & !val)
+    });
+    let ctx = Tofino { map, pci: Box::new(sim) };
+
+    let asserted = survey(&ctx, "device_select.lfltr").unwrap();
+    assert_eq!(asserted.len(), 1);
+    assert_eq!(asserted[0].value, 0b1010);
+    assert!(asserted[0].fields.iter().all(|f| f.en0.is_some()));
+
+    // Clearing one field must leave the others pending.
+    let field = ["filter_unavail".to_string()];
+    clear(&ctx, "device_select.lfltr.1.ctrl.intr_stat", &field, Format::Json)
+        .unwrap();
+    assert_eq!(ctx.pci.read4(stat).unwrap(), 0b1000);
+
+    clear(&ctx, "device_select.lfltr.1", &[], Format::Json).unwrap();
+    assert!(survey(&ctx, "device_select.lfltr").unwrap().is_empty());
+}
@@ -7,48 +7,95 @@
 #![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
 
+use std::path::PathBuf;
+
 use anyhow::{Context, Result, anyhow, bail};
 use chrono::Utc;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use regs::map::{FieldMode, Node, RegMap, Register, join_path};
 use rust_rpi::Platform;
+use serde::Serialize;
+use tofino::backend::Backend;
 
 mod dr;
+mod ecc;
 mod fuse;
+mod intr;
 mod mac;
+mod pll;
+mod port;
+mod pvt;
+mod reset;
+mod snapshot;
+mod spi;
+mod tv80;
+mod watch;
 
 const REGISTER_SIZE: usize = 72 * 1024 * 1024;
 
-mod tofino_regs {
-    use anyhow::{Result, anyhow};
-
-    pub struct Node {
-        pub size: u32,
-    }
-    pub struct RegMap {}
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The ASIC to operate on: an instance number as shown by
+    /// `list-devices`, a devfs path, a device path, or a fuse chip ID.  May
+    /// be omitted if there is only one ASIC.
+    #[clap(short, long, global = true)]
+    device: Option<String>,
 
-    impl RegMap {
-        pub fn new() -> Result<Self> {
-            Ok(RegMap {})
-        }
+    /// Read registers from a snapshot saved by `snapshot save`, rather than
+    /// from an ASIC.
+    #[clap(long, global = true, conflicts_with = "device")]
+    snapshot: Option<PathBuf>,
 
-        pub fn get_node(&self, _reg: &str) -> Result<&Node> {
-            Err(anyhow!("no register map available"))
-        }
+    /// How to present the results of the command.
+    #[clap(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
 
-        pub fn get_offset(&self, _reg: &str) -> Result<u32> {
-            Err(anyhow!("no register map available"))
-        }
-    }
+    #[clap(subcommand)]
+    command: TftoolCommand,
+}
 
-    pub fn get_children(_node: &Node) -> Vec<String> {
-        Vec::new()
-    }
+/// The output formats supported by tftool.  The JSON documents emitted by
+/// each command are described alongside the code that builds them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Human-readable tables.
+    Text,
+    /// A single JSON document on stdout.
+    Json,
 }
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Subcommand)]
 pub enum TftoolCommand {
+    /// List the Tofino ASICs found on this system.
+    ListDevices,
+
     /// Dump the content of the fuse registers.
-    Fuse,
+    Fuse {
+        /// Decode fuse data saved in a file, rather than reading the ASIC.
+        /// The file holds the 16 words as hex text, unless --binary is given.
+        #[clap(long, conflicts_with = "raw")]
+        from_file: Option<PathBuf>,
+
+        /// The file given to --from-file holds the 16 words as raw
+        /// little-endian binary.
+        #[clap(long, requires = "from_file")]
+        binary: bool,
+
+        /// Display the raw fuse words rather than decoding them.
+        #[clap(long)]
+        raw: bool,
+
+        /// Save the raw fuse words to a file, for decoding with --from-file.
+        #[clap(long, requires = "raw")]
+        save: Option<PathBuf>,
+
+        /// Group the fuse's disable and limit fields by what they restrict,
+        /// rather than listing every field.  Only the pipe enables are
+        /// decoded: the port, speed, MAU stage and frequency fields are
+        /// shown as raw bits and codes, as their layout isn't documented.
+        #[clap(long, conflicts_with = "raw")]
+        restrictions: bool,
+    },
 
     #[clap(subcommand)]
     Dr(DrCommands),
@@ -58,6 +105,250 @@ pub enum TftoolCommand {
 
     #[clap(subcommand)]
     Mac(MacCommands),
+
+    #[clap(subcommand)]
+    Port(PortCommands),
+
+    #[clap(subcommand)]
+    Snapshot(SnapshotCommands),
+
+    #[clap(subcommand)]
+    Intr(IntrCommands),
+
+    /// Report the memories whose ECC or parity error logs have recorded an
+    /// error.
+    Ecc {
+        /// Limit the survey to the logs beneath this path.
+        #[clap(default_value = ".")]
+        path: String,
+
+        /// Reset the logs that have recorded errors, and clear their
+        /// interrupt status bits, after reporting them.
+        #[clap(long)]
+        reset: bool,
+    },
+
+    /// Show the raw codes held by the process/voltage/temperature sensor,
+    /// from whatever conversion it last completed.  No conversion is
+    /// triggered, and the codes aren't converted to degrees or millivolts,
+    /// as neither is documented.
+    Pvt,
+
+    /// Show the clock PLLs' raw control registers, and whether each has
+    /// latched a loss of lock.  The control registers aren't decoded, as
+    /// their layout isn't documented.
+    Pll,
+
+    #[clap(subcommand)]
+    Reset(ResetCommands),
+
+    #[clap(subcommand)]
+    Spi(SpiCommands),
+
+    #[clap(subcommand)]
+    Tv80(Tv80Commands),
+
+    /// Poll registers, reporting each change to their fields.
+    Watch {
+        /// The registers to watch.  Any component of the path may be a
+        /// pattern using `*` and `?`, and a block covers every register
+        /// beneath it.
+        reg: String,
+
+        /// The time between polls, in milliseconds.
+        #[clap(short, long, default_value = "1000")]
+        interval: u64,
+
+        /// Stop once a condition holds, e.g. `eth_status0.linkup != 0xff`.
+        /// The register may be given as the tail of a watched path, and may
+        /// be followed by one of its fields.  A field may be given as the
+        /// tail of its name, following an underscore, if that is unique.
+        #[clap(long)]
+        until: Option<String>,
+    },
+}
+
+/// Survey and clear interrupt status registers.
+#[derive(Debug, Subcommand)]
+pub enum IntrCommands {
+    /// List the asserted bits of every interrupt status register, along with
+    /// their enable and freeze state.
+    Show {
+        /// Limit the survey to the registers beneath this path.
+        #[clap(default_value = ".")]
+        path: String,
+    },
+
+    /// Clear the interrupt status registers at or beneath a path.
+    Clear {
+        /// The status register, or the block containing them, to clear.
+        path: String,
+        /// Clear only these fields, rather than every asserted bit.
+        fields: Vec<String>,
+    },
+}
+
+/// Reset individual subsystems of the ASIC.
+#[derive(Debug, Subcommand)]
+pub enum ResetCommands {
+    /// Show the reset control registers and the MACs held in reset.
+    Status,
+
+    /// Reset a MAC.
+    Mac {
+        /// The MAC to reset: `aux`, `cpu`, or a number between 1-32.
+        mac: String,
+    },
+
+    /// Reset the traffic manager, and wait for its blocks to report ready.
+    Tm {
+        /// The bit of misc_regs.soft_reset that resets it, which isn't
+        /// documented in tf2.rsf.
+        #[clap(long)]
+        soft_reset_bit: u32,
+    },
+
+    /// Reset a pipe.
+    Pipe {
+        pipe: u32,
+        /// The bit of misc_regs.soft_reset that resets it, which isn't
+        /// documented in tf2.rsf.
+        #[clap(long)]
+        soft_reset_bit: u32,
+    },
+
+    /// Reset the PCIe controller, and wait for the ASIC to answer reads.
+    Pcie {
+        /// The bit of misc_regs.soft_reset that resets it, which isn't
+        /// documented in tf2.rsf.
+        #[clap(long)]
+        soft_reset_bit: u32,
+        /// Reset the controller, even though that cuts off the host's
+        /// access to the ASIC until the link comes back.
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+/// Access the ASIC's boot flash through its SPI master.  The layout of the
+/// SPI engine's command register hasn't been confirmed against a datasheet,
+/// so every command that drives it must be given `--unconfirmed`.
+#[derive(Debug, Subcommand)]
+pub enum SpiCommands {
+    /// Read the flash's JEDEC ID, and compare it with the ID read by the
+    /// boot firmware.
+    Id {
+        /// Drive the SPI engine even though its command layout has not been
+        /// confirmed against a datasheet.
+        #[clap(long)]
+        unconfirmed: bool,
+    },
+
+    /// Read from the flash, printing a hex dump or saving it to a file.
+    Read {
+        /// The address to start reading from.
+        addr: String,
+        /// The number of bytes to read.
+        len: String,
+        /// Save the data to this file.
+        #[clap(short, long)]
+        out: Option<PathBuf>,
+        /// Drive the SPI engine even though its command layout has not been
+        /// confirmed against a datasheet.
+        #[clap(long)]
+        unconfirmed: bool,
+    },
+
+    /// Erase the flash and program an image into it, then verify it.  The
+    /// image must start at the beginning of a sector.  The flash's ID must
+    /// match the one read by the boot firmware.
+    Write {
+        /// The file holding the image.
+        file: PathBuf,
+        /// The address at which to write the image.
+        #[clap(long, default_value = "0")]
+        addr: String,
+        /// Drive the SPI engine even though its command layout has not been
+        /// confirmed against a datasheet.
+        #[clap(long)]
+        unconfirmed: bool,
+    },
+
+    /// Compare the contents of the flash with an image.
+    Verify {
+        /// The file holding the image.
+        file: PathBuf,
+        /// The address at which the image is expected.
+        #[clap(long, default_value = "0")]
+        addr: String,
+        /// Drive the SPI engine even though its command layout has not been
+        /// confirmed against a datasheet.
+        #[clap(long)]
+        unconfirmed: bool,
+    },
+}
+
+/// Debug the TV80 microcontrollers.  A core is named `misc`, `aux` or
+/// `cpu` for the aux MAC's, or a number between 1-32 for a 400G MAC's.
+#[derive(Debug, Subcommand)]
+pub enum Tv80Commands {
+    /// Report whether each core is halted, along with its stall-on-error,
+    /// watchdog and trace buffer state.
+    Status {
+        /// The core to report on, rather than all of them.
+        core: Option<String>,
+    },
+
+    /// Stall a core, and wait for it to halt.
+    Halt { core: String },
+
+    /// Release a stalled core, and wait for it to run.
+    Resume { core: String },
+
+    /// Print the entries in a core's trace buffer.
+    Trace {
+        core: String,
+        /// Mark the entries as consumed once they have been printed.
+        #[clap(long)]
+        consume: bool,
+    },
+
+    /// Dump a core's memory window, as a hex dump or to a file.
+    Dump {
+        core: String,
+        /// The byte offset within the window to start from.
+        #[clap(long, default_value = "0")]
+        addr: String,
+        /// The number of bytes to dump, by default the rest of the window.
+        #[clap(long)]
+        len: Option<String>,
+        /// Save the memory to this file.
+        #[clap(short, long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Load an image into a halted core's memory window.
+    Load {
+        core: String,
+        /// The file holding the image.
+        file: PathBuf,
+        /// The word-aligned byte offset within the window to load it at.
+        #[clap(long, default_value = "0")]
+        addr: String,
+    },
+}
+
+/// Capture and compare the state of every register on the device.
+#[derive(Debug, Subcommand)]
+pub enum SnapshotCommands {
+    /// Save the value of every readable register to a file.
+    Save {
+        /// The file to write the snapshot to.
+        file: PathBuf,
+    },
+
+    /// Report the registers and fields which differ between two snapshots.
+    Diff { a: PathBuf, b: PathBuf },
 }
 
 /// Dump info about descriptor rings.
@@ -65,12 +356,31 @@ pub enum TftoolCommand {
 pub enum DrCommands {
     /// Show the register values for a single descriptor ring.
     Show {
-        /// The descriptor ring.
+        /// The descriptor ring, by name (as listed by `dr dump`) or path.
         dr: String,
     },
 
     /// Dump summary information for all descriptor rings.
     Dump,
+
+    /// Sample descriptor rings repeatedly, reporting their occupancy and
+    /// the rates at which they are produced and consumed.  Rings that are
+    /// stalled, disabled with entries pending, or whose memory is backwards
+    /// or overlapping are flagged, along with any read errors they have
+    /// logged and the DMA state of their buses.  Rings whose memory isn't
+    /// 8-byte aligned draw a warning.
+    Monitor {
+        /// The rings to monitor, by name or path.  Defaults to every ring.
+        rings: Vec<String>,
+
+        /// The time between samples, in milliseconds.
+        #[clap(short, long, default_value = "1000")]
+        interval: u64,
+
+        /// The number of samples to take.
+        #[clap(short, long, default_value = "5")]
+        count: u32,
+    },
 }
 
 /// Display MAC register state.
@@ -83,21 +393,58 @@ pub enum MacCommands {
     },
 }
 
+/// Display the state of the front-panel ports.
+#[derive(Debug, Subcommand)]
+pub enum PortCommands {
+    /// Show the link, fault and signal state of one or all ports.
+    Status {
+        /// The port to display, or a connector to display all of its ports.
+        port: Option<String>,
+
+        /// The board's port map, with a line for each port giving its name,
+        /// connector, MAC and lanes, e.g. `1/0 qsfp1 5 0-3`.  Without one,
+        /// each MAC carries a single port named for its number.
+        #[clap(long)]
+        board: Option<PathBuf>,
+    },
+}
+
 /// Operate on Tofino registers.
 #[derive(Debug, Subcommand)]
 pub enum RegCommands {
-    /// Read the contents of a register.
+    /// Read the contents of a register.  A named register, or array of
+    /// registers, is decoded into its fields.
     Read {
         /// The register to read.
         reg: String,
         num: Option<u32>,
+
+        /// Display the raw register words rather than decoding the fields.
+        #[clap(long)]
+        raw: bool,
     },
 
     /// Modify the contents of a register.
     Write {
         /// The register to write to.
         reg: String,
+        /// The value to write.  This may be a single value, which is split
+        /// into words for a wide register; a comma-separated list of words,
+        /// in address order; or a comma-separated list of `field=value`
+        /// pairs.  Fields not named are left unchanged.
         val: String,
+
+        /// Accept a list of fewer words than the register holds, writing
+        /// only its first words.
+        #[clap(long)]
+        partial: bool,
+
+        /// Write fields of an interrupt status register, even though the
+        /// register map doesn't record which of its bits are
+        /// write-1-to-clear.  Any asserted status bit written back may be
+        /// cleared.
+        #[clap(long)]
+        force: bool,
     },
 
     /// List the children of the node in the given register path.
@@ -124,19 +471,25 @@ pub enum RegCommands {
 }
 
 pub struct Tofino {
-    map: tofino_regs::RegMap,
-    pci: tofino::pci::Pci,
+    map: RegMap,
+    pci: Box<dyn Backend>,
 }
 
 impl Tofino {
     pub fn new(dev_path: String) -> Result<Self> {
-        let map = tofino_regs::RegMap::new()?;
         let pci = tofino::pci::Pci::new(&dev_path, REGISTER_SIZE)?;
+        Self::with_backend(Box::new(pci))
+    }
+
+    /// Build a context that accesses registers through the given backend,
+    /// which may be a simulated ASIC rather than a real one.
+    pub fn with_backend(pci: Box<dyn Backend>) -> Result<Self> {
+        let map = RegMap::new()?;
         Ok(Tofino { map, pci })
     }
 
     // Get the node inside the register tree corresponding to this path
-    fn get_node(&self, reg: &str) -> Result<&tofino_regs::Node> {
+    fn get_node(&self, reg: &str) -> Result<Node<'_>> {
         self.map.get_node(reg)
     }
 
@@ -146,8 +499,8 @@ impl Tofino {
     }
 
     // Get all the children of the given node.
-    fn get_children(&self, node: &tofino_regs::Node) -> Result<Vec<String>> {
-        Ok(tofino_regs::get_children(node))
+    fn get_children(&self, node: &Node) -> Result<Vec<String>> {
+        Ok(node.children())
     }
 }
 
@@ -175,59 +528,114 @@ fn search_in(
     max: u32,
     path: &str,
     tgt: &str,
+    found: &mut Vec<String>,
 ) -> Result<()> {
     let node = ctx
         .get_node(path)
         .with_context(|| format!("Attempting to get node for {path}"))?;
     let children = ctx
-        .get_children(node)
+        .get_children(&node)
         .with_context(|| format!("Attempting to get children of {path}"))?;
 
     if children.is_empty() {
         if path.contains(tgt) {
             *cnt += 1;
             if *cnt <= max {
-                println!("{}", path);
+                found.push(path.to_string());
             }
         }
     } else {
         for name in &children {
-            let next = format!("{}.{}", path, name);
-            search_in(ctx, cnt, max, &next, tgt)?;
+            let next = join_path(path, name);
+            search_in(ctx, cnt, max, &next, tgt, found)?;
         }
     }
     Ok(())
 }
 
-pub fn search(ctx: &mut Tofino, max: u32, tgt: String) -> Result<()> {
+/// The JSON form of `reg search`: the first `max` matching register paths,
+/// and the total number of matches.
+#[derive(Serialize)]
+struct SearchReport {
+    matches: Vec<String>,
+    total: u32,
+}
+
+pub fn search(
+    ctx: &mut Tofino,
+    max: u32,
+    tgt: String,
+    format: Format,
+) -> Result<()> {
     let path = String::from(".");
 
     let mut cnt = 0;
-    search_in(ctx, &mut cnt, max, &path, &tgt)?;
-
-    if cnt > max {
-        println!("...");
-        println!("{} matches found", cnt);
+    let mut found = Vec::new();
+    search_in(ctx, &mut cnt, max, &path, &tgt, &mut found)?;
+    if cnt == 0 {
+        bail!("not found");
     }
 
-    match cnt {
-        0 => Err(anyhow!("not found")),
-        _ => Ok(()),
+    match format {
+        Format::Json => {
+            print_json(&SearchReport { matches: found, total: cnt })
+        }
+        Format::Text => {
+            for path in found {
+                println!("{}", path);
+            }
+            if cnt > max {
+                println!("...");
+                println!("{} matches found", cnt);
+            }
+            Ok(())
+        }
     }
 }
 
-fn list(ctx: &Tofino, path: String) -> Result<()> {
+// The JSON form of `reg list` is an array of the child names.
+fn list(ctx: &Tofino, path: String, format: Format) -> Result<()> {
     let node = ctx.get_node(&path)?;
-    for c in ctx.get_children(node)? {
-        if !c.starts_with('_') {
-            println!("{}", c);
-        }
+    let children = ctx
+        .get_children(&node)?
+        .into_iter()
+        .filter(|c| !c.starts_with('_'))
+        .collect::<Vec<String>>();
+    match format {
+        Format::Json => print_json(&children)?,
+        Format::Text => children.iter().for_each(|c| println!("{}", c)),
     }
     Ok(())
 }
 
+/// Print a command's result as a pretty-printed JSON document.
+pub(crate) fn print_json<T: Serialize + ?Sized>(val: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(val)?);
+    Ok(())
+}
+
+/// Print bytes 16 to a line, each line labeled with its address.
+pub(crate) fn hexdump(addr: u32, data: &[u8]) {
+    for (i, line) in data.chunks(16).enumerate() {
+        let bytes = line
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<String>>()
+            .join(" ");
+        println!("{:06x}: {bytes}", addr as usize + 16 * i);
+    }
+}
+
+// Decide whether a register can be read through the BAR with aligned 4-byte
+// accesses.  The pipes lie beyond the end of it, and the spacing given for a
+// few arrays in tf2.rsf leaves some of their entries unaligned.
+pub(crate) fn reachable(node: &Node) -> bool {
+    node.offset.is_multiple_of(4)
+        && node.offset as usize + node.size as usize <= REGISTER_SIZE
+}
+
 pub fn read_offset(
-    ctx: &mut Tofino,
+    ctx: &Tofino,
     mut offset: u32,
     cnt: u32,
 ) -> Result<Vec<u32>> {
@@ -253,8 +661,134 @@ fn write_offset(ctx: &mut Tofino, offset: u32, val: u32) -> Result<()> {
     ctx.pci.write4(offset, val)
 }
 
-fn cmd_read(ctx: &mut Tofino, reg: &str, cnt: Option<u32>) -> Result<()> {
+/// The JSON form of `reg read`.  `register` is the path that was read, or
+/// null if the user gave a raw offset.  `words` holds the consecutive 32-bit
+/// words starting at `offset`.  Unless `--raw` is given, a named register is
+/// decoded into `fields`, and an array of registers is emitted as an array of
+/// these documents.
+#[derive(Serialize)]
+struct ReadReport {
+    register: Option<String>,
+    offset: u32,
+    words: Vec<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<Vec<FieldReport>>,
+}
+
+/// A single decoded field.  `value` is null for a write-only field, and
+/// `reset` is null if the register map doesn't record a reset value.
+#[derive(Serialize)]
+struct FieldReport {
+    name: String,
+    msb: u32,
+    lsb: u32,
+    mode: String,
+    value: Option<u64>,
+    reset: Option<u64>,
+}
+
+// Read a single register and decode each of its fields.
+fn decode_register(
+    ctx: &Tofino,
+    path: String,
+    offset: u32,
+    reg: &Register,
+) -> Result<ReadReport> {
+    let words = read_offset(ctx, offset, reg.width.div_ceil(32))
+        .with_context(|| format!("reading {path}"))?;
+    let val = reg.value(&words)?;
+    let fields = reg
+        .fields
+        .iter()
+        .map(|f| FieldReport {
+            name: f.name.clone(),
+            msb: f.msb(),
+            lsb: f.lsb,
+            mode: f.mode.to_string(),
+            value: f.mode.is_readable().then(|| f.extract(val)),
+            reset: reg.reset_value.map(|r| f.extract(r)),
+        })
+        .collect();
+    Ok(ReadReport { register: Some(path), offset, words, fields: Some(fields) })
+}
+
+fn print_decoded(report: &ReadReport) {
+    let hex = |v: Option<u64>| v.map_or("-".to_string(), |v| format!("{v:#x}"));
+    let words = report
+        .words
+        .iter()
+        .rev()
+        .map(|w| format!("{w:08x}"))
+        .collect::<Vec<String>>()
+        .join("_");
+    println!(
+        "{} @ {:#x}: {}",
+        report.register.as_deref().unwrap_or("-"),
+        report.offset,
+        words
+    );
+    println!(
+        "  {:32} {:>7} {:4} {:>18} {:>18}",
+        "FIELD", "BITS", "MODE", "VALUE", "RESET"
+    );
+    for f in report.fields.iter().flatten() {
+        let bits = match f.msb == f.lsb {
+            true => format!("{}", f.lsb),
+            false => format!("{}:{}", f.msb, f.lsb),
+        };
+        println!(
+            "  {:32} {:>7} {:4} {:>18} {:>18}",
+            f.name,
+            bits,
+            f.mode,
+            hex(f.value),
+            hex(f.reset)
+        );
+    }
+}
+
+// Decode a register, or each register in an array.  Returns false if the
+// node doesn't describe registers.
+fn cmd_decode(ctx: &Tofino, reg: &str, format: Format) -> Result<bool> {
+    let Ok(node) = ctx.get_node(reg) else {
+        return Ok(false);
+    };
+    let mut reports = Vec::new();
+    if let Some(def) = node.register() {
+        reports.push(decode_register(ctx, reg.to_string(), node.offset, def)?);
+    } else if let (Some(_), regs::map::Definition::Register(def)) =
+        (node.array, node.def)
+    {
+        for idx in node.children() {
+            let path = join_path(reg, &idx);
+            let offset = ctx.get_offset(&path)?;
+            reports.push(decode_register(ctx, path, offset, def)?);
+        }
+    } else {
+        return Ok(false);
+    }
+
+    match (format, node.array) {
+        (Format::Json, None) => print_json(&reports[0])?,
+        (Format::Json, Some(_)) => print_json(&reports)?,
+        (Format::Text, _) => reports.iter().for_each(print_decoded),
+    }
+    Ok(true)
+}
+
+fn cmd_read(
+    ctx: &mut Tofino,
+    reg: &str,
+    cnt: Option<u32>,
+    raw: bool,
+    format: Format,
+) -> Result<()> {
     let mut cnt = cnt.unwrap_or(1);
+    let mut register = None;
+
+    if !raw && parse_val(reg).is_err() && cmd_decode(ctx, reg, format)? {
+        return Ok(());
+    }
 
     // First try to parse the "reg" as a raw hex offset.
     let mut offset = if let Ok(offset) = parse_val(reg) {
@@ -263,12 +797,21 @@ fn cmd_read(ctx: &mut Tofino, reg: &str, cnt: Option<u32>) -> Result<()> {
     // Now try as a register name.
     } else if let Ok(node) = ctx.get_node(reg) {
         cnt = node.size / 4;
+        register = Some(reg.to_string());
         Ok(ctx.get_offset(reg)?)
     } else {
         Err(anyhow!("bad register/offset: {}", reg))
     }?;
 
     let vals = read_offset(ctx, offset, cnt)?;
+    if format == Format::Json {
+        return print_json(&ReadReport {
+            register,
+            offset,
+            words: vals,
+            fields: None,
+        });
+    }
     for val in vals {
         println!(
             "{}{:x}",
@@ -284,19 +827,158 @@ fn cmd_read(ctx: &mut Tofino, reg: &str, cnt: Option<u32>) -> Result<()> {
     Ok(())
 }
 
-// XXX: todo- add support for writing multi-word registers?
-// add support for writing bitfields?
-fn cmd_write(ctx: &mut Tofino, reg: &str, val: &str) -> Result<()> {
-    let offset = if let Ok(offset) = parse_val(reg) {
-        Ok(offset)
-    } else if let Ok(offset) = ctx.get_offset(reg) {
-        Ok(offset)
+// Parse a list of "field=value" assignments, and apply them to the current
+// value of the register.
+fn assign_fields(reg: &Register, mut current: u64, list: &str) -> Result<u64> {
+    for assignment in list.split(',') {
+        let (name, val) = assignment
+            .split_once('=')
+            .ok_or(anyhow!("expected field=value, found {assignment}"))?;
+        let name = name.trim();
+        let field = reg
+            .fields
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or(anyhow!("{} has no field {}", reg.name, name))?;
+        if !field.mode.is_writable() {
+            bail!("field {name} is read-only");
+        }
+        let val = parse_u64(val.trim())?;
+        if val & !field.mask() != 0 {
+            bail!(
+                "{val:#x} does not fit in the {}-bit field {name}",
+                field.width
+            );
+        }
+        current = field.insert(current, val);
+    }
+    Ok(current)
+}
+
+// Perform a read-modify-write of the named fields of a register.  Writing
+// back the asserted bits of an interrupt status register would clear them,
+// so unless the map says which bits are write-1-to-clear, such registers are
+// refused without `force`.
+fn cmd_write_fields(
+    ctx: &mut Tofino,
+    path: &str,
+    list: &str,
+    force: bool,
+) -> Result<()> {
+    let node = ctx.get_node(path)?;
+    let reg =
+        node.register().ok_or(anyhow!("{path} is not a single register"))?;
+    let w1c_known =
+        reg.fields.iter().any(|f| f.mode == FieldMode::ReadWrite1Clear);
+    if intr::is_status(path, reg) && !w1c_known && !force {
+        bail!(
+            "{path} holds interrupt status bits, which would be cleared by \
+             writing them back; use --force to write it anyway"
+        );
+    }
+    let words = reg.width.div_ceil(32);
+    let current = reg.value(&read_offset(ctx, node.offset, words)?)?;
+    let current = reg.rmw_base(current);
+    let val = assign_fields(reg, current, list)?;
+    let words: Vec<u32> =
+        (0..words).map(|w| (val >> (32 * w)) as u32).collect();
+    write_words(ctx, path, node.offset, &words)
+}
+
+// Parse the value to be written as a list of words in address order.  The
+// value is either a comma-separated list of words, or a single number which
+// is split into `nwords` words, least significant first.  If `nwords` isn't
+// known, the number is split into as many words as it needs.  A list must
+// have exactly `nwords` words, unless `partial` allows it to have fewer.
+fn parse_words(
+    val: &str,
+    nwords: Option<u32>,
+    partial: bool,
+) -> Result<Vec<u32>> {
+    if val.contains(',') {
+        let words = val
+            .split(',')
+            .map(|w| parse_val(w.trim()))
+            .collect::<Result<Vec<u32>>>()?;
+        match nwords {
+            Some(n) if words.len() > n as usize => bail!(
+                "{} words given, but the register has only {n}",
+                words.len()
+            ),
+            Some(n) if words.len() < n as usize && !partial => bail!(
+                "{} words given, but the register has {n}; use --partial \
+                 to write only the first {}",
+                words.len(),
+                words.len()
+            ),
+            _ => {}
+        }
+        return Ok(words);
+    }
+
+    let wide = match val.strip_prefix("0x") {
+        Some(x) => u128::from_str_radix(x, 16),
+        None => val.parse::<u128>(),
+    }
+    .map_err(|e| anyhow!("invalid value {val}: {e}"))?;
+    let needed = (128 - wide.leading_zeros()).div_ceil(32).max(1);
+    let nwords = nwords.unwrap_or(needed);
+    if needed > nwords {
+        bail!("{val} does not fit in {nwords} words");
+    }
+    Ok((0..nwords).map(|w| (wide >> (32 * w)) as u32).collect())
+}
+
+// Write a list of words to consecutive offsets.  The words are written in
+// ascending address order, so the most significant word of a wide register
+// is written last, as the hardware expects.  If a write fails part way
+// through, the error says how many words made it to the device.
+fn write_words(
+    ctx: &mut Tofino,
+    what: &str,
+    offset: u32,
+    words: &[u32],
+) -> Result<()> {
+    for (i, word) in words.iter().enumerate() {
+        let addr = offset + 4 * i as u32;
+        write_offset(ctx, addr, *word).with_context(|| match i {
+            0 => format!("writing {what}"),
+            _ => format!(
+                "partial write of {what}: wrote {i} of {} words before \
+                 failing at {addr:#x}",
+                words.len()
+            ),
+        })?;
+    }
+    Ok(())
+}
+
+fn cmd_write(
+    ctx: &mut Tofino,
+    reg: &str,
+    val: &str,
+    partial: bool,
+    force: bool,
+) -> Result<()> {
+    if val.contains('=') {
+        return cmd_write_fields(ctx, reg, val, force);
+    }
+
+    let (offset, nwords) = if let Ok(offset) = parse_val(reg) {
+        Ok((offset, None))
+    } else if let Ok(node) = ctx.get_node(reg) {
+        // Only a single register, or an opaque wide one, can be written by
+        // name.  Splatting a value across a whole block is never intended.
+        if node.register().is_none() && !node.is_opaque() {
+            bail!("{reg} is not a single register");
+        }
+        Ok((node.offset, Some(node.size / 4).filter(|n| *n > 0)))
     } else {
         Err(anyhow!("bad register/offset: {}", reg))
     }?;
 
-    let val = parse_val(val)?;
-    write_offset(ctx, offset, val)
+    let words = parse_words(val, nwords, partial)?;
+    write_words(ctx, reg, offset, &words)
 }
 
 fn parse_val(v: &str) -> Result<u32> {
@@ -380,41 +1062,208 @@ fn perf(ctx: &mut Tofino, iter: usize) -> Result<()> {
     Ok(())
 }
 
-fn mac_command(ctx: &mut Tofino, cmd: MacCommands) -> Result<()> {
+fn mac_command(
+    ctx: &mut Tofino,
+    cmd: MacCommands,
+    format: Format,
+) -> Result<()> {
     match cmd {
-        MacCommands::Status { mac } => mac::status(ctx, mac),
+        MacCommands::Status { mac } => mac::status(ctx, mac, format),
     }
 }
 
-fn reg_command(ctx: &mut Tofino, cmd: RegCommands) -> Result<()> {
+fn reg_command(
+    ctx: &mut Tofino,
+    cmd: RegCommands,
+    format: Format,
+) -> Result<()> {
     match cmd {
-        RegCommands::Read { reg, num } => cmd_read(ctx, &reg, num),
-        RegCommands::Write { reg, val } => cmd_write(ctx, &reg, &val),
-        RegCommands::List { reg } => list(ctx, reg),
-        RegCommands::Search { max, reg } => search(ctx, max, reg),
+        RegCommands::Read { reg, num, raw } => {
+            cmd_read(ctx, &reg, num, raw, format)
+        }
+        RegCommands::Write { reg, val, partial, force } => {
+            cmd_write(ctx, &reg, &val, partial, force)
+        }
+        RegCommands::List { reg } => list(ctx, reg, format),
+        RegCommands::Search { max, reg } => search(ctx, max, reg, format),
+        RegCommands::Perf { .. } if format == Format::Json => {
+            bail!("perf does not support JSON output")
+        }
         RegCommands::Perf { n } => perf(ctx, n),
     }
 }
 
+// The JSON form of `list-devices` is an array of the discovered nodes, as
+// described by `tofino::TofinoNode`.
+fn list_devices(format: Format) -> Result<()> {
+    if format == Format::Json {
+        return print_json(&tofino::get_tofino_nodes()?);
+    }
+    println!(
+        "{:>4} {:9} {:5} {:5} {:18} {:28} {:10}",
+        "INST", "SUBSYS", "MODEL", "AVAIL", "CHIP ID", "WAFER", "DEVFS PATH"
+    );
+    for node in tofino::get_tofino_nodes()? {
+        let (chip, wafer) = match node.chip_id {
+            Some(id) => (
+                format!("{id:#018x}"),
+                tofino::fuse::ChipId::from(id).to_string(),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        println!(
+            "{:>4} {:#09x} {:5} {:5} {:18} {:28} {}",
+            node.instance.map_or("-".to_string(), |i| i.to_string()),
+            node.subsystem_id,
+            format!("{}{}", node.generation, node.stepping),
+            node.available,
+            chip,
+            wafer,
+            node.devfs_path
+        );
+    }
+    Ok(())
+}
+
+// Determine whether the given tofino node is the one identified by the
+// --device selector.
+fn device_matches(node: &tofino::TofinoNode, selector: &str) -> bool {
+    if let Ok(instance) = selector.parse::<i32>() {
+        return node.instance == Some(instance);
+    }
+    if node.devfs_path == selector {
+        return true;
+    }
+    if node.device_path().is_ok_and(|p| p == selector) {
+        return true;
+    }
+    match (parse_u64(selector), node.chip_id) {
+        (Ok(selected), Some(chip_id)) => selected == chip_id,
+        _ => false,
+    }
+}
+
+fn parse_u64(v: &str) -> Result<u64> {
+    match v.strip_prefix("0x") {
+        Some(x) => u64::from_str_radix(x, 16)
+            .map_err(|e| anyhow!("invalid hex value: {:?}", e)),
+        None => v.parse::<u64>().map_err(|e| anyhow!("invalid value: {:?}", e)),
+    }
+}
+
+// Find the tofino asic to operate on.  If the user didn't specify one, there
+// must be exactly one on the system.
+fn select_device(selector: Option<&str>) -> Result<tofino::TofinoNode> {
+    let mut nodes = tofino::get_tofino_nodes()?;
+    let node = match selector {
+        Some(sel) => match nodes.iter().position(|n| device_matches(n, sel)) {
+            Some(idx) => nodes.swap_remove(idx),
+            None => bail!("no tofino asic matches {sel}"),
+        },
+        None => match nodes.len() {
+            0 => bail!("no tofino asic found"),
+            1 => nodes.pop().unwrap(),
+            n => bail!("found {n} tofino asics.  Use --device to select one"),
+        },
+    };
+
+    if !node.available {
+        bail!("tofino not available");
+    }
+    Ok(node)
+}
+
+// Cross-check the PCI identity of the device against its fuse data.  A fuse
+// that identifies the part as a Tofino 1 is fatal, while any other mismatch
+// is reported but allowed.
+fn check_fuse(node: &tofino::TofinoNode, ctx: &Tofino) -> Result<()> {
+    let fuse = tofino::fuse::Fuse::read(ctx.pci.as_ref())?;
+    if let Err(e) = node.check_fuse(&fuse) {
+        if let Some((tofino::Generation::Tofino1, _)) = fuse.model() {
+            return Err(e);
+        }
+        eprintln!("warning: {e}");
+    }
+    Ok(())
+}
+
+// Open the selected ASIC, after checking that it is one we can operate on.
+fn open_device(selector: Option<&str>) -> Result<Tofino> {
+    let node = select_device(selector)?;
+    // The register map, and every command built on it, describes the Tofino
+    // 2.  Applying it to any other part would silently return garbage.
+    if node.generation != tofino::Generation::Tofino2 {
+        bail!(
+            "device is a {} {}, but tftool only supports the {}",
+            node.generation,
+            node.stepping,
+            tofino::Generation::Tofino2
+        );
+    }
+    let ctx = Tofino::new(node.device_path()?)?;
+    check_fuse(&node, &ctx)?;
+    Ok(ctx)
+}
+
 pub fn exec() -> Result<()> {
     // Parse this first to display help if requested.
-    let command = TftoolCommand::parse();
+    let args = Args::parse();
 
-    let dev = match tofino::get_tofino()? {
-        Some(node) => {
-            if !node.available {
-                bail!("tofino not available");
-            }
-            node.device_path()?
+    // These commands don't operate on an ASIC.
+    match &args.command {
+        TftoolCommand::ListDevices => return list_devices(args.format),
+        TftoolCommand::Fuse {
+            from_file: Some(path),
+            binary,
+            restrictions,
+            ..
+        } => {
+            return fuse::dump_file(path, *binary, *restrictions, args.format);
         }
-        None => bail!("no tofino asic found"),
+        TftoolCommand::Snapshot(SnapshotCommands::Diff { a, b }) => {
+            return snapshot::show_diff(a, b, args.format);
+        }
+        _ => {}
+    }
+
+    let mut ctx = match &args.snapshot {
+        Some(path) => snapshot::open(path)?,
+        None => open_device(args.device.as_deref())?,
     };
-    let mut ctx = Tofino::new(dev)?;
 
-    match command {
-        TftoolCommand::Fuse => fuse::dump_fuse(&mut ctx),
-        TftoolCommand::Reg(reg_cmd) => reg_command(&mut ctx, reg_cmd),
-        TftoolCommand::Mac(mac_cmd) => mac_command(&mut ctx, mac_cmd),
-        TftoolCommand::Dr(dr_cmd) => dr::dr_command(&mut ctx, dr_cmd),
+    match args.command {
+        TftoolCommand::ListDevices => unreachable!(),
+        TftoolCommand::Snapshot(SnapshotCommands::Diff { .. }) => {
+            unreachable!()
+        }
+        TftoolCommand::Snapshot(SnapshotCommands::Save { file }) => {
+            snapshot::save(&ctx, &file, args.format)
+        }
+        TftoolCommand::Intr(cmd) => intr::intr_command(&ctx, cmd, args.format),
+        TftoolCommand::Pvt => pvt::pvt(&ctx, args.format),
+        TftoolCommand::Pll => pll::pll(&ctx, args.format),
+        TftoolCommand::Spi(cmd) => spi::spi_command(&ctx, cmd, args.format),
+        TftoolCommand::Tv80(cmd) => tv80::tv80_command(&ctx, cmd, args.format),
+        TftoolCommand::Reset(cmd) => {
+            reset::reset_command(&ctx, cmd, args.format)
+        }
+        TftoolCommand::Ecc { path, reset } => {
+            ecc::ecc(&ctx, &path, reset, args.format)
+        }
+        TftoolCommand::Watch { reg, interval, until } => {
+            watch::watch(&ctx, &reg, interval, until.as_deref(), args.format)
+        }
+        TftoolCommand::Fuse { raw: true, save, .. } => {
+            fuse::dump_raw(&mut ctx, save.as_deref(), args.format)
+        }
+        TftoolCommand::Fuse { restrictions, .. } => {
+            fuse::dump_fuse(&mut ctx, restrictions, args.format)
+        }
+        TftoolCommand::Reg(cmd) => reg_command(&mut ctx, cmd, args.format),
+        TftoolCommand::Mac(cmd) => mac_command(&mut ctx, cmd, args.format),
+        TftoolCommand::Port(cmd) => {
+            port::port_command(&mut ctx, cmd, args.format)
+        }
+        TftoolCommand::Dr(cmd) => dr::dr_command(&ctx, cmd, args.format),
     }
 }
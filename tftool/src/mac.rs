@@ -5,28 +5,31 @@
 // Copyright 2023 Oxide Computer Company
 
 use anyhow::{Result, anyhow};
+use serde::Serialize;
 
-use crate::{Tofino, read_register};
+use crate::{Format, Tofino, print_json, read_register};
 use tofino::common::{get_bit, get_bits};
 
 // Each field contains one bit of state for each of 4 channels
-struct Eth100GStatus {
-    macsts_sigok: u8,
-    macsts_txidle: u8,
-    macsts_rxidle: u8,
-    macsts_txgood: u8,
+#[derive(Serialize)]
+pub(crate) struct Eth100GStatus {
+    pub(crate) macsts_sigok: u8,
+    pub(crate) macsts_txidle: u8,
+    pub(crate) macsts_rxidle: u8,
+    pub(crate) macsts_txgood: u8,
 }
 
 // Each field contains one bit of state for each of 8 channels
-struct Eth400GStatus {
-    macsts_lfault: u8,
-    macsts_rfault: u8,
-    macsts_ofault: u8,
-    macsts_linkup: u8,
-    macsts_sigok: u8,
-    macsts_txidle: u8,
-    macsts_rxidle: u8,
-    macsts_txgood: u8,
+#[derive(Serialize)]
+pub(crate) struct Eth400GStatus {
+    pub(crate) macsts_lfault: u8,
+    pub(crate) macsts_rfault: u8,
+    pub(crate) macsts_ofault: u8,
+    pub(crate) macsts_linkup: u8,
+    pub(crate) macsts_sigok: u8,
+    pub(crate) macsts_txidle: u8,
+    pub(crate) macsts_rxidle: u8,
+    pub(crate) macsts_txgood: u8,
 }
 
 #[allow(dead_code)]
@@ -35,7 +38,18 @@ struct Eth400GhIntStat {
     intr_hi_stat: u8,
 }
 
-fn eth100g_status(ctx: &mut Tofino) -> Result<Eth100GStatus> {
+/// The JSON form of `mac status`.  Each status field is a bitmask with one
+/// bit per channel, with channel 0 in the least significant bit.  The aux MAC
+/// has 4 channels and no fault or link state, so those fields are omitted.
+/// When no MAC is given, the output is an array of all the 400G MACs.
+#[derive(Serialize)]
+struct MacReport<T> {
+    mac: String,
+    #[serde(flatten)]
+    status: T,
+}
+
+pub(crate) fn eth100g_status(ctx: &mut Tofino) -> Result<Eth100GStatus> {
     let val = read_register(ctx, "eth100g_regs.eth100g_reg.eth_status", 1)?;
     Ok(Eth100GStatus {
         macsts_sigok: get_bits(&val, 0, 3) as u8,
@@ -45,7 +59,10 @@ fn eth100g_status(ctx: &mut Tofino) -> Result<Eth100GStatus> {
     })
 }
 
-fn eth400g_status(ctx: &mut Tofino, mac: u32) -> Result<Eth400GStatus> {
+pub(crate) fn eth400g_status(
+    ctx: &mut Tofino,
+    mac: u32,
+) -> Result<Eth400GStatus> {
     let base = format!("eth400g_p{}.eth400g_mac", mac);
     let path0 = format!("{}.eth_status0", base);
     let path1 = format!("{}.eth_status1", base);
@@ -111,7 +128,7 @@ fn show_all_eth400g(ctx: &mut Tofino) -> Result<()> {
         "txgood"
     );
 
-    for mac in 1..32 {
+    for mac in 1..=32 {
         let s = eth400g_status(ctx, mac)?;
         println!(
             "{:3} {:6x} {:6x} {:6x} {:6x} {:6x} {:6x} {:6x} {:6x}",
@@ -151,7 +168,36 @@ fn show_aux(ctx: &mut Tofino) -> Result<()> {
     Ok(())
 }
 
-pub fn status(ctx: &mut Tofino, mac: Option<String>) -> Result<()> {
+fn json_status(ctx: &mut Tofino, mac: Option<String>) -> Result<()> {
+    if let Some(mac) = mac {
+        if mac.eq_ignore_ascii_case("aux") || mac.eq_ignore_ascii_case("cpu") {
+            let status = eth100g_status(ctx)?;
+            print_json(&MacReport { mac: "aux".to_string(), status })
+        } else if let Ok(n) = mac.parse::<u32>() {
+            let status = eth400g_status(ctx, n)?;
+            print_json(&MacReport { mac, status })
+        } else {
+            Err(anyhow!("invalid mac: {}", mac))
+        }
+    } else {
+        let mut macs = Vec::new();
+        for mac in 1..=32 {
+            let status = eth400g_status(ctx, mac)?;
+            macs.push(MacReport { mac: mac.to_string(), status });
+        }
+        print_json(&macs)
+    }
+}
+
+pub fn status(
+    ctx: &mut Tofino,
+    mac: Option<String>,
+    format: Format,
+) -> Result<()> {
+    if format == Format::Json {
+        return json_status(ctx, mac);
+    }
+
     if let Some(mac) = mac {
         if mac.eq_ignore_ascii_case("aux") || mac.eq_ignore_ascii_case("cpu") {
             show_aux(ctx)
@@ -0,0 +1,33 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use anyhow::Result;
+use tofino::pll;
+
+use crate::{Format, Tofino, print_json};
+
+// The JSON form of `pll` is an array of `tofino::pll::PllStatus`.
+pub fn pll(ctx: &Tofino, format: Format) -> Result<()> {
+    let all = pll::read_all(ctx.pci.as_ref())?;
+    if format == Format::Json {
+        return print_json(&all);
+    }
+
+    println!("{:5} {:>10} {:>10} lol", "pll", "ctrl0", "ctrl1");
+    for p in &all {
+        println!(
+            "{:5} {:#010x} {:#010x} {}",
+            p.pll.to_string(),
+            p.ctrl[0],
+            p.ctrl[1],
+            match p.lost_lock {
+                true => "latched",
+                false => "-",
+            },
+        );
+    }
+    Ok(())
+}
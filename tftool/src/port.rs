@@ -0,0 +1,163 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use anyhow::Result;
+use serde::Serialize;
+use tofino::port::{Port, PortMap};
+
+use crate::mac::{eth100g_status, eth400g_status};
+use crate::{Format, PortCommands, Tofino, print_json};
+
+/// The state of a port's channel, and of each of its lanes.  The CPU MAC has
+/// no link or fault state, so those fields are null for its ports.
+#[derive(Serialize)]
+struct PortStatus {
+    link: Option<bool>,
+    local_fault: Option<bool>,
+    remote_fault: Option<bool>,
+    other_fault: Option<bool>,
+    /// Whether each of the port's lanes has a good signal, first lane first
+    signal_ok: Vec<bool>,
+}
+
+/// The JSON form of `port status`, which emits an array of these
+#[derive(Serialize)]
+struct PortReport<'a> {
+    #[serde(flatten)]
+    port: &'a Port,
+    channel: u32,
+    status: PortStatus,
+}
+
+fn port_status(ctx: &mut Tofino, port: &Port) -> Result<PortStatus> {
+    let bit = |mask: u8, n: u32| (mask >> n) & 1 == 1;
+    let lanes = |mask: u8| port.lanes().map(|l| bit(mask, l)).collect();
+    let ch = port.channel();
+    let status = match port.mac {
+        0 => {
+            let s = eth100g_status(ctx)?;
+            PortStatus {
+                link: None,
+                local_fault: None,
+                remote_fault: None,
+                other_fault: None,
+                signal_ok: lanes(s.macsts_sigok),
+            }
+        }
+        mac => {
+            let s = eth400g_status(ctx, mac)?;
+            PortStatus {
+                link: Some(bit(s.macsts_linkup, ch)),
+                local_fault: Some(bit(s.macsts_lfault, ch)),
+                remote_fault: Some(bit(s.macsts_rfault, ch)),
+                other_fault: Some(bit(s.macsts_ofault, ch)),
+                signal_ok: lanes(s.macsts_sigok),
+            }
+        }
+    };
+    Ok(status)
+}
+
+fn status_line(port: &Port, s: &PortStatus) -> String {
+    let lanes = match port.lane_count {
+        1 => port.first_lane.to_string(),
+        _ => format!("{}-{}", port.first_lane, port.lanes().end - 1),
+    };
+    let head = format!(
+        "{:8} {:10} {:>3} {:5}",
+        port.name,
+        port.connector.as_deref().unwrap_or("-"),
+        port.mac,
+        lanes
+    );
+    let link = match s.link {
+        Some(true) => "up",
+        Some(false) => "down",
+        None => "-",
+    };
+    let faults: Vec<&str> = [
+        (s.local_fault, "local"),
+        (s.remote_fault, "remote"),
+        (s.other_fault, "other"),
+    ]
+    .into_iter()
+    .filter(|(f, _)| *f == Some(true))
+    .map(|(_, name)| name)
+    .collect();
+    let faults = match faults.is_empty() {
+        true => "-".to_string(),
+        false => faults.join(","),
+    };
+    let signal: String =
+        s.signal_ok.iter().map(|ok| if *ok { '1' } else { '0' }).collect();
+    format!("{head} {link:5} {faults:18} {signal}")
+}
+
+pub fn port_command(
+    ctx: &mut Tofino,
+    cmd: PortCommands,
+    format: Format,
+) -> Result<()> {
+    let PortCommands::Status { port, board } = cmd;
+    let map = match board {
+        Some(path) => PortMap::load(&path)?,
+        None => PortMap::default(),
+    };
+    let ports = match &port {
+        Some(name) => map.find(name)?,
+        None => map.ports().iter().collect(),
+    };
+
+    let mut reports = Vec::new();
+    for port in ports {
+        let status = port_status(ctx, port)?;
+        reports.push(PortReport { port, channel: port.channel(), status });
+    }
+    if format == Format::Json {
+        return print_json(&reports);
+    }
+
+    println!(
+        "{:8} {:10} {:>3} {:5} {:5} {:18} SIGNAL",
+        "PORT", "CONNECTOR", "MAC", "LANES", "LINK", "FAULTS"
+    );
+    for r in &reports {
+        println!("{}", status_line(r.port, &r.status));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_port_status() {
+    use regs::map::RegMap;
+    use tofino::sim::SimAsic;
+
+    let map = RegMap::new().unwrap();
+    let mac = "eth400g_p5.eth400g_mac";
+    let sim = SimAsic::new();
+    // Link up on channel 4, with a remote fault on channel 0
+    let stat0 = map.get_offset(&format!("{mac}.eth_status0")).unwrap();
+    sim.set(stat0, 0x10 << 24 | 0x01 << 8);
+    // Signal on lanes 0, 1 and 4-6
+    let stat1 = map.get_offset(&format!("{mac}.eth_status1")).unwrap();
+    sim.set(stat1, 0x73);
+    let mut ctx = Tofino { map, pci: Box::new(sim) };
+
+    let text = "1/0 qsfp1 5 0-3\n1/1 qsfp1 5 4-7";
+    let board = PortMap::parse(text).unwrap();
+    let ports = board.find("qsfp1").unwrap();
+    let lines: Vec<String> = ports
+        .iter()
+        .map(|p| status_line(p, &port_status(&mut ctx, p).unwrap()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "1/0      qsfp1        5 0-3   down  remote             1100",
+            "1/1      qsfp1        5 4-7   up    -                  1110",
+        ]
+    );
+}
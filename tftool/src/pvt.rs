@@ -0,0 +1,54 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use anyhow::Result;
+use serde::Serialize;
+use tofino::fuse::Fuse;
+use tofino::pvt::Pvt;
+
+use crate::{Format, Tofino, print_json};
+
+/// The JSON form of `pvt`: the sensor's registers, along with the voltage
+/// and process characterization burned into the fuse.
+#[derive(Serialize)]
+struct PvtReport {
+    #[serde(flatten)]
+    pvt: Pvt,
+    voltage_scaling: u64,
+    pmro_and_skew: u64,
+}
+
+pub fn pvt(ctx: &Tofino, format: Format) -> Result<()> {
+    let pvt = Pvt::read(ctx.pci.as_ref())?;
+    let fuse = Fuse::read(ctx.pci.as_ref())?;
+    let report = PvtReport {
+        pvt,
+        voltage_scaling: fuse.voltage_scaling,
+        pmro_and_skew: fuse.pmro_and_skew,
+    };
+    if format == Format::Json {
+        return print_json(&report);
+    }
+
+    let p = &report.pvt;
+    println!("{:24}: {:#x}", "pvt_ctrl", p.ctrl);
+    println!(
+        "{:24}: {:#x}{}",
+        "code",
+        p.code,
+        match p.valid {
+            true => "",
+            false => " (not valid)",
+        }
+    );
+    println!(
+        "{:24}: below {:#x}, above {:#x}",
+        "alarm thresholds", p.alarm_lo, p.alarm_hi
+    );
+    println!("{:24}: {:#x}", "voltage_scaling", report.voltage_scaling);
+    println!("{:24}: {:#x}", "pmro_and_skew", report.pmro_and_skew);
+    Ok(())
+}
@@ -0,0 +1,78 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use anyhow::Result;
+use serde::Serialize;
+use tofino::reset::{self, ResetState, Subsystem};
+
+use crate::{Format, ResetCommands, Tofino, print_json};
+
+/// The JSON form of a completed reset
+#[derive(Serialize)]
+struct ResetReport {
+    reset: Subsystem,
+    state: ResetState,
+}
+
+fn show(state: &ResetState) {
+    println!("soft_reset:       {:#010x}", state.soft_reset);
+    println!("reset_option:     {:#010x}", state.reset_option);
+    println!("pcie half period: {}", state.pcie_reset_half_period);
+    println!("dbg_rst:          {:#010x}", state.dbg_rst);
+    let in_reset = match state.in_reset.is_empty() {
+        true => "none".to_string(),
+        false => state
+            .in_reset
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>()
+            .join(" "),
+    };
+    println!("in reset:         {in_reset}");
+}
+
+// The JSON form of `reset status` is a `tofino::reset::ResetState`.
+pub fn reset_command(
+    ctx: &Tofino,
+    cmd: ResetCommands,
+    format: Format,
+) -> Result<()> {
+    let pci = ctx.pci.as_ref();
+    let (sub, bit, force) = match cmd {
+        ResetCommands::Status => {
+            let state = ResetState::read(pci)?;
+            return match format {
+                Format::Json => print_json(&state),
+                Format::Text => {
+                    show(&state);
+                    Ok(())
+                }
+            };
+        }
+        ResetCommands::Mac { mac } => {
+            (Subsystem::parse_mac(&mac)?, None, false)
+        }
+        ResetCommands::Tm { soft_reset_bit } => {
+            (Subsystem::Tm, Some(soft_reset_bit), false)
+        }
+        ResetCommands::Pipe { pipe, soft_reset_bit } => {
+            (Subsystem::Pipe(pipe), Some(soft_reset_bit), false)
+        }
+        ResetCommands::Pcie { soft_reset_bit, force } => {
+            (Subsystem::Pcie, Some(soft_reset_bit), force)
+        }
+    };
+
+    reset::reset(pci, sub, bit, force)?;
+    let state = ResetState::read(pci)?;
+    match format {
+        Format::Json => print_json(&ResetReport { reset: sub, state }),
+        Format::Text => {
+            println!("{sub} has been reset");
+            Ok(())
+        }
+    }
+}
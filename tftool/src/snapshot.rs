@@ -0,0 +1,473 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
+use regs::map::{Node, RegMap, Register};
+use serde::Serialize;
+use tofino::backend::Backend;
+
+use crate::{Format, Tofino, print_json, reachable};
+
+const SNAPSHOT_HEADER: &str = "# tftool register snapshot";
+const SNAPSHOT_VERSION: u32 = 1;
+
+// Data ports whose reads have side effects which no field mode describes:
+// reading an indirect memory access window performs the access, and reading
+// a ring's data port consumes an entry.  They are matched against the name of
+// the register within its block.
+const SIDE_EFFECT_REGISTERS: [&str; 2] = ["indir_access_data", "ring_rdata"];
+
+// The copy of tf2.rsf in the tree doesn't record which fields are
+// clear-on-read, so the map alone can't identify them.  Counters and error
+// logs are the registers most likely to clear when read, so anything named
+// like one is skipped too.  This errs towards skipping registers that are
+// safe to read, and may still miss some that aren't.
+const COUNTER_REGISTERS: [&str; 4] = ["cnt", "count", "err_log", "errlog"];
+
+/// The value of a single register at the time of the snapshot
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub offset: u32,
+    pub value: u64,
+}
+
+/// The state of every readable register on the device.
+///
+/// On disk, a snapshot is a text file with a header of "# key: value"
+/// comments, followed by one "offset value path" line per register.
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    pub version: u32,
+    pub chip_id: Option<u64>,
+    pub taken: String,
+    pub registers: Vec<Entry>,
+}
+
+impl Snapshot {
+    /// A snapshot of the given registers, taken now
+    pub fn new(chip_id: Option<u64>, registers: Vec<Entry>) -> Self {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            chip_id,
+            taken: Utc::now().to_rfc3339(),
+            registers,
+        }
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> Result<()> {
+        writeln!(w, "{SNAPSHOT_HEADER}")?;
+        writeln!(w, "# version: {}", self.version)?;
+        if let Some(id) = self.chip_id {
+            writeln!(w, "# chip_id: {id:#018x}")?;
+        }
+        writeln!(w, "# taken: {}", self.taken)?;
+        for e in &self.registers {
+            writeln!(w, "{:#010x} {:#010x} {}", e.offset, e.value, e.path)?;
+        }
+        Ok(())
+    }
+
+    pub fn parse(data: &str) -> Result<Snapshot> {
+        let mut lines = data.lines().enumerate();
+        if lines.next().map(|(_, l)| l.trim()) != Some(SNAPSHOT_HEADER) {
+            bail!("not a tftool snapshot");
+        }
+
+        let mut snap = Snapshot {
+            version: 0,
+            chip_id: None,
+            taken: String::new(),
+            registers: Vec::new(),
+        };
+        for (line_no, line) in lines {
+            let err = |e: anyhow::Error| anyhow!("line {}: {e}", line_no + 1);
+            let line = line.trim();
+            if let Some(meta) = line.strip_prefix('#') {
+                let Some((key, val)) = meta.split_once(':') else {
+                    continue;
+                };
+                let val = val.trim();
+                match key.trim() {
+                    "version" => {
+                        snap.version = val.parse().map_err(|e| {
+                            err(anyhow!("invalid version {val}: {e}"))
+                        })?
+                    }
+                    "chip_id" => {
+                        snap.chip_id = Some(parse_hex(val).map_err(err)?)
+                    }
+                    "taken" => snap.taken = val.to_string(),
+                    _ => {}
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            if snap.version != SNAPSHOT_VERSION {
+                bail!("unsupported snapshot version {}", snap.version);
+            }
+            let mut fields = line.split_whitespace();
+            let (Some(offset), Some(value), Some(path), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(err(anyhow!("malformed entry: {line}")));
+            };
+            snap.registers.push(Entry {
+                path: path.to_string(),
+                offset: parse_hex(offset).map_err(err)? as u32,
+                value: parse_hex(value).map_err(err)?,
+            });
+        }
+        Ok(snap)
+    }
+}
+
+fn parse_hex(v: &str) -> Result<u64> {
+    let x = v.strip_prefix("0x").ok_or(anyhow!("expected hex: {v}"))?;
+    u64::from_str_radix(x, 16).map_err(|e| anyhow!("invalid hex {v}: {e}"))
+}
+
+// Decide whether a register can be captured.  Registers with no readable
+// fields, registers with clear-on-read fields, the data ports, counters and
+// error logs above, and registers outside the mapped BAR are all skipped, as
+// are opaque wide registers, whose values can't be decoded.  Clear-on-read
+// detection is incomplete until tf2.rsf records access modes.
+fn capturable(path: &str, node: &Node, reg: &Register) -> bool {
+    let name =
+        path.rsplit('.').find(|c| c.parse::<u32>().is_err()).unwrap_or(path);
+    !(reg.fields.iter().all(|f| !f.mode.is_readable())
+        || reg.clears_on_read()
+        || SIDE_EFFECT_REGISTERS.iter().any(|s| name.contains(s))
+        || COUNTER_REGISTERS.iter().any(|s| name.contains(s))
+        || !reachable(node))
+}
+
+/// Read every capturable register on the device.  Returns the snapshot and
+/// the number of registers skipped.
+pub fn capture(ctx: &Tofino) -> Result<(Snapshot, usize)> {
+    let chip_id = tofino::fuse::Fuse::read(ctx.pci.as_ref())?.chip_id;
+    let mut registers = Vec::new();
+    let mut skipped = 0;
+    ctx.map.walk_leaves("", &mut |path, node| {
+        let reg = match node.register() {
+            Some(reg) if capturable(path, node, reg) => reg,
+            _ => {
+                skipped += 1;
+                return Ok(());
+            }
+        };
+        let words = crate::read_offset(ctx, node.offset, node.size / 4)
+            .with_context(|| format!("reading {path}"))?;
+        registers.push(Entry {
+            path: path.to_string(),
+            offset: node.offset,
+            value: reg.value(&words)?,
+        });
+        Ok(())
+    })?;
+
+    Ok((Snapshot::new(Some(chip_id), registers), skipped))
+}
+
+/// A field whose value differs between two snapshots
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub name: String,
+    pub before: u64,
+    pub after: u64,
+}
+
+/// A register which differs between two snapshots.  `before` or `after` is
+/// None if the register only appears in one of them.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub path: String,
+    pub offset: u32,
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Compare two snapshots, breaking each changed register down by field.
+pub fn diff(map: &RegMap, a: &Snapshot, b: &Snapshot) -> Vec<Change> {
+    let mut all: BTreeMap<&str, (u32, Option<u64>, Option<u64>)> =
+        BTreeMap::new();
+    for e in &a.registers {
+        all.entry(&e.path).or_insert((e.offset, None, None)).1 = Some(e.value);
+    }
+    for e in &b.registers {
+        all.entry(&e.path).or_insert((e.offset, None, None)).2 = Some(e.value);
+    }
+
+    let mut changes = Vec::new();
+    for (path, (offset, before, after)) in all {
+        if before == after {
+            continue;
+        }
+        let mut fields = Vec::new();
+        if let (Some(before), Some(after)) = (before, after)
+            && let Some(reg) =
+                map.get_node(path).ok().and_then(|n| n.register())
+        {
+            for f in &reg.fields {
+                let (x, y) = (f.extract(before), f.extract(after));
+                if x != y {
+                    fields.push(FieldChange {
+                        name: f.name.clone(),
+                        before: x,
+                        after: y,
+                    });
+                }
+            }
+        }
+        changes.push(Change {
+            path: path.to_string(),
+            offset,
+            before,
+            after,
+            fields,
+        });
+    }
+    changes
+}
+
+fn load(path: &Path) -> Result<Snapshot> {
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("reading {}", path.display()))?;
+    Snapshot::parse(&data)
+        .with_context(|| format!("parsing {}", path.display()))
+}
+
+/// A read-only backend which serves register reads from a snapshot
+pub struct SnapshotBackend {
+    words: HashMap<u32, u32>,
+}
+
+impl SnapshotBackend {
+    /// Index the words of every register in the snapshot.  The map supplies
+    /// the width of each register.
+    pub fn new(map: &RegMap, snap: &Snapshot) -> Self {
+        let mut words = HashMap::new();
+        for e in &snap.registers {
+            let size = map.get_node(&e.path).map_or(4, |n| n.size.max(4));
+            for word in 0..size / 4 {
+                let val = e.value.checked_shr(32 * word).unwrap_or(0);
+                words.insert(e.offset + 4 * word, val as u32);
+            }
+        }
+        SnapshotBackend { words }
+    }
+}
+
+impl Backend for SnapshotBackend {
+    fn read4(&self, offset: u32) -> Result<u32> {
+        self.words.get(&offset).copied().ok_or(anyhow!(
+            "the register at {offset:#x} was not captured in the snapshot"
+        ))
+    }
+
+    fn write4(&self, offset: u32, _val: u32) -> Result<()> {
+        bail!("cannot write to {offset:#x}: the snapshot is read-only")
+    }
+}
+
+/// Build a context which reads registers from a saved snapshot.
+pub fn open(path: &Path) -> Result<Tofino> {
+    let map = RegMap::new()?;
+    let snap = load(path)?;
+    let pci = Box::new(SnapshotBackend::new(&map, &snap));
+    Ok(Tofino { map, pci })
+}
+
+/// The JSON form of `snapshot save`: the number of registers written to the
+/// file, and the number skipped.
+#[derive(Serialize)]
+struct SaveReport {
+    saved: usize,
+    skipped: usize,
+}
+
+pub fn save(ctx: &Tofino, file: &Path, format: Format) -> Result<()> {
+    let (snap, skipped) = capture(ctx)?;
+    let mut f = std::io::BufWriter::new(
+        std::fs::File::create(file)
+            .with_context(|| format!("creating {}", file.display()))?,
+    );
+    snap.write(&mut f)
+        .and_then(|_| f.flush().map_err(|e| e.into()))
+        .with_context(|| format!("writing {}", file.display()))?;
+
+    let saved = snap.registers.len();
+    match format {
+        Format::Json => print_json(&SaveReport { saved, skipped })?,
+        Format::Text => println!(
+            "saved {saved} registers to {} ({skipped} skipped)",
+            file.display()
+        ),
+    }
+    Ok(())
+}
+
+// The JSON form of `snapshot diff` is an array of `Change`s.
+pub fn show_diff(a: &Path, b: &Path, format: Format) -> Result<()> {
+    let map = RegMap::new()?;
+    let (a, b) = (load(a)?, load(b)?);
+    if a.chip_id != b.chip_id {
+        eprintln!("warning: snapshots were taken from different devices");
+    }
+    let changes = diff(&map, &a, &b);
+    if format == Format::Json {
+        return print_json(&changes);
+    }
+
+    let hex = |v: Option<u64>| v.map_or("-".to_string(), |v| format!("{v:#x}"));
+    for c in &changes {
+        println!(
+            "{} @ {:#x}: {} -> {}",
+            c.path,
+            c.offset,
+            hex(c.before),
+            hex(c.after)
+        );
+        for f in &c.fields {
+            println!("    {}: {:#x} -> {:#x}", f.name, f.before, f.after);
+        }
+    }
+    println!("{} registers differ", changes.len());
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_diff() {
+    use regs::map::{BlockDef, ElementDef, FieldDef, FieldMode, RegisterDef};
+
+    let map = RegMap::from_defs(
+        &[
+            BlockDef {
+                name: "Main",
+                doc: "",
+                elements: &[
+                    ElementDef {
+                        name: "ctrl",
+                        doc: "",
+                        typ: "Ctrl",
+                        offset: 0x0,
+                        array: Some((2, 4)),
+                    },
+                    ElementDef {
+                        name: "pending",
+                        doc: "",
+                        typ: "Pending",
+                        offset: 0x8,
+                        array: None,
+                    },
+                    ElementDef {
+                        name: "drop_cnt",
+                        doc: "",
+                        typ: "Ctrl",
+                        offset: 0xc,
+                        array: None,
+                    },
+                    ElementDef {
+                        name: "wide",
+                        doc: "",
+                        typ: "Wide",
+                        offset: 0x10,
+                        array: Some((2, 0x10)),
+                    },
+                ],
+            },
+            BlockDef { name: "Wide", doc: "", elements: &[] },
+        ],
+        &[
+            RegisterDef {
+                name: "Ctrl",
+                doc: "",
+                width: 32,
+                reset: None,
+                fields: &[
+                    FieldDef {
+                        name: "enable",
+                        doc: "",
+                        mode: FieldMode::ReadWrite,
+                        access: None,
+                        lsb: 0x0,
+                        width: 1,
+                    },
+                    FieldDef {
+                        name: "mode",
+                        doc: "",
+                        mode: FieldMode::ReadWrite,
+                        access: None,
+                        lsb: 0x4,
+                        width: 3,
+                    },
+                ],
+            },
+            RegisterDef {
+                name: "Pending",
+                doc: "",
+                width: 32,
+                reset: None,
+                fields: &[FieldDef {
+                    name: "pending",
+                    doc: "",
+                    mode: FieldMode::ReadOnly,
+                    access: Some("rc"),
+                    lsb: 0x0,
+                    width: 32,
+                }],
+            },
+        ],
+    )
+    .unwrap();
+    let entry = |path: &str, offset, value| Entry {
+        path: path.to_string(),
+        offset,
+        value,
+    };
+    let a = Snapshot {
+        version: SNAPSHOT_VERSION,
+        chip_id: Some(0x1234),
+        taken: "then".to_string(),
+        registers: vec![entry("ctrl.0", 0, 0x11), entry("ctrl.1", 4, 0)],
+    };
+    let mut out = Vec::new();
+    a.write(&mut out).unwrap();
+    assert_eq!(Snapshot::parse(std::str::from_utf8(&out).unwrap()).unwrap(), a);
+
+    let b = Snapshot { registers: vec![entry("ctrl.0", 0, 0x51)], ..a };
+    let a = Snapshot::parse(std::str::from_utf8(&out).unwrap()).unwrap();
+    let changes = diff(&map, &a, &b);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(
+        changes[0].fields,
+        vec![FieldChange { name: "mode".to_string(), before: 1, after: 5 }]
+    );
+    assert_eq!((changes[1].before, changes[1].after), (Some(0), None));
+
+    let backend = SnapshotBackend::new(&map, &b);
+    assert_eq!(backend.read4(0).unwrap(), 0x51);
+    assert!(backend.read4(4).is_err());
+    assert!(backend.write4(0, 0).is_err());
+
+    // Only the two ctrl registers can be captured: pending is clear-on-read,
+    // drop_cnt is named like a counter and wide is opaque.
+    let sim = tofino::sim::SimAsic::new();
+    sim.set(0x4, 0x11);
+    let ctx = Tofino { map, pci: Box::new(sim) };
+    let (snap, skipped) = capture(&ctx).unwrap();
+    let values: Vec<u64> = snap.registers.iter().map(|e| e.value).collect();
+    assert_eq!((values, skipped), (vec![0, 0x11], 4));
+}
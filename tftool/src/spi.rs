@@ -0,0 +1,134 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use tofino::spi::{self, Engine, JedecId};
+
+use crate::{Format, SpiCommands, Tofino, hexdump, parse_u64, print_json};
+
+/// The JSON form of `spi id`
+#[derive(Serialize)]
+struct IdReport {
+    id: JedecId,
+    size: Option<u64>,
+    /// The ID read by the boot firmware, which should match
+    boot_id: JedecId,
+}
+
+/// The JSON form of `spi read`, with the data as a hex string
+#[derive(Serialize)]
+struct ReadReport {
+    addr: u32,
+    data: String,
+}
+
+/// The JSON form of `spi write` and `spi verify`.  `mismatch` is the
+/// address of the first byte that differs from the image, if any.
+#[derive(Serialize)]
+struct VerifyReport {
+    addr: u32,
+    len: usize,
+    mismatch: Option<u32>,
+}
+
+fn parse_addr(v: &str) -> Result<u32> {
+    let addr = parse_u64(v)?;
+    if addr >= spi::ADDRESS_LIMIT as u64 {
+        bail!("{v} is beyond the end of the flash");
+    }
+    Ok(addr as u32)
+}
+
+fn read_image(file: &Path) -> Result<Vec<u8>> {
+    std::fs::read(file).with_context(|| format!("reading {}", file.display()))
+}
+
+fn verify(spi: &Engine, addr: u32, image: &[u8], format: Format) -> Result<()> {
+    let mismatch = spi::verify(spi, addr, image)?;
+    match format {
+        Format::Json => {
+            print_json(&VerifyReport { addr, len: image.len(), mismatch })?
+        }
+        Format::Text if mismatch.is_none() => {
+            println!("verified {} bytes at {addr:#x}", image.len())
+        }
+        Format::Text => {}
+    }
+    match mismatch {
+        Some(m) => bail!("flash differs from the image at {m:#x}"),
+        None => Ok(()),
+    }
+}
+
+pub fn spi_command(
+    ctx: &Tofino,
+    cmd: SpiCommands,
+    format: Format,
+) -> Result<()> {
+    let pci = ctx.pci.as_ref();
+    match cmd {
+        SpiCommands::Id { unconfirmed } => {
+            let id = spi::read_id(&Engine::new(pci, unconfirmed)?)?;
+            let report =
+                IdReport { id, size: id.size(), boot_id: spi::boot_id(pci)? };
+            match format {
+                Format::Json => print_json(&report)?,
+                Format::Text => {
+                    println!(
+                        "manufacturer {:#04x} type {:#04x} capacity {:#04x}",
+                        id.manufacturer, id.memory_type, id.capacity
+                    );
+                    if let Some(size) = report.size {
+                        println!("size: {} KiB", size / 1024);
+                    }
+                    if report.boot_id != id {
+                        println!(
+                            "boot firmware read a different ID: {:?}",
+                            report.boot_id
+                        );
+                    }
+                }
+            }
+            Ok(())
+        }
+        SpiCommands::Read { addr, len, out, unconfirmed } => {
+            let spi = Engine::new(pci, unconfirmed)?;
+            let addr = parse_addr(&addr)?;
+            let len = parse_u64(&len)? as usize;
+            let data = spi::read(&spi, addr, len)?;
+            if let Some(out) = out {
+                return std::fs::write(&out, &data)
+                    .with_context(|| format!("writing {}", out.display()));
+            }
+            match format {
+                Format::Json => {
+                    let data =
+                        data.iter().map(|b| format!("{b:02x}")).collect();
+                    print_json(&ReadReport { addr, data })
+                }
+                Format::Text => {
+                    hexdump(addr, &data);
+                    Ok(())
+                }
+            }
+        }
+        SpiCommands::Write { file, addr, unconfirmed } => {
+            let spi = Engine::new(pci, unconfirmed)?;
+            let addr = parse_addr(&addr)?;
+            let image = read_image(&file)?;
+            spi::write(&spi, addr, &image)?;
+            verify(&spi, addr, &image, format)
+        }
+        SpiCommands::Verify { file, addr, unconfirmed } => {
+            let spi = Engine::new(pci, unconfirmed)?;
+            let addr = parse_addr(&addr)?;
+            verify(&spi, addr, &read_image(&file)?, format)
+        }
+    }
+}
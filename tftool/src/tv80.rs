@@ -0,0 +1,490 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use regs::map::Register;
+use serde::Serialize;
+
+use crate::{
+    Format, Tofino, Tv80Commands, hexdump, parse_u64, print_json, read_offset,
+};
+
+/// The size of each core's memory window.  tf2.rsf covers it with an array
+/// of 0x1000 opaque words.
+const WINDOW_SIZE: u32 = 0x4000;
+
+/// How long to wait for a core to stop or start
+const HALT_POLLS: u32 = 100;
+const HALT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// One of the TV80 microcontrollers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Core {
+    Misc,
+    /// The core in the aux (CPU) MAC
+    Aux,
+    /// The core in one of the 400G MACs, numbered 1-32
+    Mac(u32),
+}
+
+impl Core {
+    fn parse(s: &str) -> Result<Core> {
+        match s.to_lowercase().as_str() {
+            "misc" => Ok(Core::Misc),
+            "aux" | "cpu" => Ok(Core::Aux),
+            n => match n.parse::<u32>() {
+                Ok(mac) if (1..=32).contains(&mac) => Ok(Core::Mac(mac)),
+                _ => bail!("invalid core {s}: expected misc, aux, cpu or 1-32"),
+            },
+        }
+    }
+
+    fn all() -> Vec<Core> {
+        let mut all = vec![Core::Misc, Core::Aux];
+        all.extend((1..=32).map(Core::Mac));
+        all
+    }
+
+    // The block holding the core's control registers
+    fn regs(&self) -> String {
+        match self {
+            Core::Misc => "device_select.misc_regs".to_string(),
+            Core::Aux => "eth100g_regs.eth100g_reg".to_string(),
+            Core::Mac(n) => format!("eth400g_p{n}.eth400g_mac"),
+        }
+    }
+
+    // The block mapping the core's memory
+    fn window(&self) -> String {
+        match self {
+            Core::Misc => "device_select.misc_tv80_regs".to_string(),
+            Core::Aux => "eth100g_regs.eth100g_tv80".to_string(),
+            Core::Mac(n) => format!("eth400g_p{n}.eth400g_tv80"),
+        }
+    }
+}
+
+impl fmt::Display for Core {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Core::Misc => write!(f, "misc"),
+            Core::Aux => write!(f, "aux"),
+            Core::Mac(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+/// The value of one of a core's control registers
+struct Reg<'a> {
+    path: String,
+    offset: u32,
+    def: &'a Register,
+    value: u64,
+}
+
+impl<'a> Reg<'a> {
+    fn read(ctx: &'a Tofino, core: Core, name: &str) -> Result<Self> {
+        let path = format!("{}.{name}", core.regs());
+        let node = ctx.map.get_node(&path)?;
+        let def = node.register().ok_or(anyhow!("{path} is not a register"))?;
+        let value = ctx.pci.read4(node.offset)? as u64;
+        Ok(Reg { path, offset: node.offset, def, value })
+    }
+
+    fn field(&self, name: &str) -> Result<u64> {
+        self.def
+            .fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| f.extract(self.value))
+            .ok_or(anyhow!("{} has no field {name}", self.path))
+    }
+
+    fn write_field(&self, ctx: &Tofino, name: &str, val: u64) -> Result<()> {
+        let f = self
+            .def
+            .fields
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or(anyhow!("{} has no field {name}", self.path))?;
+        let new = f.insert(self.def.rmw_base(self.value), val);
+        ctx.pci.write4(self.offset, new as u32)
+    }
+}
+
+/// The JSON form of `tv80 status`, which is an array of these when no core
+/// is given.  The trace buffer occupies the words of the memory window
+/// between `trace_base` and `trace_limit`.  The core adds entries at
+/// `trace_tail`, and software consumes them from `trace_head`.
+#[derive(Serialize)]
+struct Tv80Status {
+    core: String,
+    halted: bool,
+    stall_absolute: bool,
+    stall_on_mbe: bool,
+    stall_on_rerr: bool,
+    watchdog_enabled: bool,
+    watchdog_timeout: u64,
+    watchdog_count: u64,
+    trace_enabled: bool,
+    trace_base: u64,
+    trace_limit: u64,
+    trace_head: u64,
+    trace_tail: u64,
+    trace_entries: usize,
+    /// The upper bits of the addresses the misc core uses to reach the
+    /// rest of the chip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    addr_msb: Option<u64>,
+}
+
+// The indices of the words holding the trace entries not yet consumed, in
+// the order they were logged.
+fn trace_words(
+    base: u64,
+    limit: u64,
+    head: u64,
+    tail: u64,
+) -> Result<Vec<u32>> {
+    if base > limit || !(base..=limit).contains(&head) {
+        bail!("trace head {head:#x} is outside {base:#x}-{limit:#x}");
+    }
+    if !(base..=limit).contains(&tail) {
+        bail!("trace tail {tail:#x} is outside {base:#x}-{limit:#x}");
+    }
+    let words = match head <= tail {
+        true => (head..tail).collect(),
+        false => (head..=limit).chain(base..tail).collect::<Vec<u64>>(),
+    };
+    Ok(words.into_iter().map(|w| w as u32).collect())
+}
+
+fn status(ctx: &Tofino, core: Core) -> Result<Tv80Status> {
+    let halted = Reg::read(ctx, core, "tv80_halted_status")?;
+    let stall = Reg::read(ctx, core, "tv80_stall_on_error")?;
+    let wd = Reg::read(ctx, core, "tv80_watchdog_ctrl")?;
+    let count = Reg::read(ctx, core, "tv80_watchdog_count")?;
+    let ctrl = Reg::read(ctx, core, "tv80_debug_ctrl")?;
+    let head =
+        Reg::read(ctx, core, "tv80_debug_head_ptr")?.field("head_ptr")?;
+    let tail =
+        Reg::read(ctx, core, "tv80_debug_tail_ptr")?.field("tail_ptr")?;
+    let (base, limit) = (ctrl.field("base_addr")?, ctrl.field("limit_addr")?);
+    let addr_msb = match core {
+        Core::Misc => Some(
+            Reg::read(ctx, core, "tv80_addr_msb")?.field("tv_80_addr_msb")?,
+        ),
+        _ => None,
+    };
+    Ok(Tv80Status {
+        core: core.to_string(),
+        halted: halted.field("tv_80_halted")? != 0,
+        stall_absolute: stall.field("stall_absolute")? != 0,
+        stall_on_mbe: stall.field("stall_on_mbe")? != 0,
+        stall_on_rerr: stall.field("stall_on_rerr")? != 0,
+        watchdog_enabled: wd.field("enable")? != 0,
+        watchdog_timeout: wd.field("timeout_value")?,
+        watchdog_count: count.field("cur_time")?,
+        trace_enabled: ctrl.field("enable")? != 0,
+        trace_base: base,
+        trace_limit: limit,
+        trace_head: head,
+        trace_tail: tail,
+        trace_entries: trace_words(base, limit, head, tail)
+            .map_or(0, |w| w.len()),
+        addr_msb,
+    })
+}
+
+fn flag(b: bool) -> &'static str {
+    match b {
+        true => "yes",
+        false => "no",
+    }
+}
+
+fn show_status(ctx: &Tofino, core: Option<Core>, format: Format) -> Result<()> {
+    let all = match core {
+        Some(core) => vec![status(ctx, core)?],
+        None => Core::all()
+            .into_iter()
+            .map(|c| status(ctx, c))
+            .collect::<Result<Vec<Tv80Status>>>()?,
+    };
+    if format == Format::Json {
+        return match core {
+            Some(_) => print_json(&all[0]),
+            None => print_json(&all),
+        };
+    }
+
+    println!(
+        "{:5} {:6} {:15} {:>10} {:>10} {:>13} {:>7}",
+        "core", "halted", "stall", "watchdog", "count", "trace", "entries"
+    );
+    for s in &all {
+        let stall = [
+            (s.stall_absolute, "abs"),
+            (s.stall_on_mbe, "mbe"),
+            (s.stall_on_rerr, "rerr"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect::<Vec<&str>>()
+        .join(",");
+        let watchdog = match s.watchdog_enabled {
+            true => format!("{:#x}", s.watchdog_timeout),
+            false => "off".to_string(),
+        };
+        let trace = match s.trace_enabled {
+            true => format!("{:#05x}-{:#05x}", s.trace_base, s.trace_limit),
+            false => "off".to_string(),
+        };
+        println!(
+            "{:5} {:6} {:15} {:>10} {:>#10x} {:>13} {:>7}",
+            s.core,
+            flag(s.halted),
+            if stall.is_empty() { "-" } else { &stall },
+            watchdog,
+            s.watchdog_count,
+            trace,
+            s.trace_entries
+        );
+        if let Some(msb) = s.addr_msb {
+            println!("{:5} addr_msb: {msb:#x}", s.core);
+        }
+    }
+    Ok(())
+}
+
+// Stall or release a core, and wait for its halted status to follow.
+fn set_halted(ctx: &Tofino, core: Core, halt: bool) -> Result<()> {
+    Reg::read(ctx, core, "tv80_stall_on_error")?.write_field(
+        ctx,
+        "stall_absolute",
+        halt as u64,
+    )?;
+    for _ in 0..HALT_POLLS {
+        let halted = Reg::read(ctx, core, "tv80_halted_status")?;
+        if (halted.field("tv_80_halted")? != 0) == halt {
+            return Ok(());
+        }
+        std::thread::sleep(HALT_POLL_INTERVAL);
+    }
+    match halt {
+        true => bail!("timed out waiting for core {core} to halt"),
+        false => bail!("timed out waiting for core {core} to resume"),
+    }
+}
+
+/// The JSON form of `tv80 trace`: each entry is the index of the word in
+/// the memory window and its value.
+#[derive(Serialize)]
+struct TraceReport {
+    core: String,
+    entries: Vec<(u32, u32)>,
+}
+
+fn trace(
+    ctx: &Tofino,
+    core: Core,
+    consume: bool,
+    format: Format,
+) -> Result<()> {
+    let s = status(ctx, core)?;
+    let words =
+        trace_words(s.trace_base, s.trace_limit, s.trace_head, s.trace_tail)?;
+    let window = ctx.map.get_offset(&core.window())?;
+    let mut entries = Vec::with_capacity(words.len());
+    for w in words {
+        entries.push((w, ctx.pci.read4(window + 4 * w)?));
+    }
+    match format {
+        Format::Json => print_json(&TraceReport {
+            core: core.to_string(),
+            entries: entries.clone(),
+        })?,
+        Format::Text => {
+            if !s.trace_enabled {
+                println!("tracing is disabled");
+            }
+            for (w, val) in &entries {
+                println!("{w:#05x}: {val:#010x}");
+            }
+        }
+    }
+    if consume {
+        Reg::read(ctx, core, "tv80_debug_head_ptr")?.write_field(
+            ctx,
+            "head_ptr",
+            s.trace_tail,
+        )?;
+    }
+    Ok(())
+}
+
+// Check that a range of bytes lies within the memory window.
+fn check_window(addr: u32, len: usize) -> Result<()> {
+    if addr as u64 + len as u64 > WINDOW_SIZE as u64 {
+        bail!("{len} bytes at {addr:#x} extend past the end of the window");
+    }
+    Ok(())
+}
+
+// Read bytes from a core's memory window.  Each word of the window holds
+// four bytes, least significant first.
+fn read_window(
+    ctx: &Tofino,
+    core: Core,
+    addr: u32,
+    len: usize,
+) -> Result<Vec<u8>> {
+    check_window(addr, len)?;
+    let window = ctx.map.get_offset(&core.window())?;
+    let first = addr / 4;
+    let last = (addr as usize + len).div_ceil(4) as u32;
+    let words = read_offset(ctx, window + 4 * first, last - first)?;
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let skip = (addr % 4) as usize;
+    Ok(bytes[skip..skip + len].to_vec())
+}
+
+// Load an image into a halted core's memory window, starting at a word
+// boundary.  A partial last word keeps its existing upper bytes.
+fn load(
+    ctx: &Tofino,
+    core: Core,
+    addr: u32,
+    file: &Path,
+    format: Format,
+) -> Result<()> {
+    if !addr.is_multiple_of(4) {
+        bail!("{addr:#x} is not word-aligned");
+    }
+    let image = std::fs::read(file)
+        .with_context(|| format!("reading {}", file.display()))?;
+    check_window(addr, image.len())?;
+    if !status(ctx, core)?.halted {
+        bail!("core {core} is running; halt it before loading memory");
+    }
+
+    let window = ctx.map.get_offset(&core.window())?;
+    for (i, chunk) in image.chunks(4).enumerate() {
+        let offset = window + addr + 4 * i as u32;
+        let mut word = ctx.pci.read4(offset)?.to_le_bytes();
+        word[..chunk.len()].copy_from_slice(chunk);
+        ctx.pci.write4(offset, u32::from_le_bytes(word))?;
+    }
+    if read_window(ctx, core, addr, image.len())? != image {
+        bail!("core {core} memory does not match the image after loading");
+    }
+    if format == Format::Text {
+        println!("loaded {} bytes at {addr:#x}", image.len());
+    }
+    Ok(())
+}
+
+pub fn tv80_command(
+    ctx: &Tofino,
+    cmd: Tv80Commands,
+    format: Format,
+) -> Result<()> {
+    match cmd {
+        Tv80Commands::Status { core } => {
+            let core = core.as_deref().map(Core::parse).transpose()?;
+            show_status(ctx, core, format)
+        }
+        Tv80Commands::Halt { core } => {
+            set_halted(ctx, Core::parse(&core)?, true)
+        }
+        Tv80Commands::Resume { core } => {
+            set_halted(ctx, Core::parse(&core)?, false)
+        }
+        Tv80Commands::Trace { core, consume } => {
+            trace(ctx, Core::parse(&core)?, consume, format)
+        }
+        Tv80Commands::Dump { core, addr, len, out } => {
+            let addr = parse_u64(&addr)? as u32;
+            let len = match len {
+                Some(len) => parse_u64(&len)? as usize,
+                None => WINDOW_SIZE.saturating_sub(addr) as usize,
+            };
+            let data = read_window(ctx, Core::parse(&core)?, addr, len)?;
+            match out {
+                Some(out) => std::fs::write(&out, &data)
+                    .with_context(|| format!("writing {}", out.display())),
+                None => {
+                    hexdump(addr, &data);
+                    Ok(())
+                }
+            }
+        }
+        Tv80Commands::Load { core, file, addr } => {
+            let addr = parse_u64(&addr)? as u32;
+            load(ctx, Core::parse(&core)?, addr, &file, format)
+        }
+    }
+}
+
+#[test]
+fn test_tv80() {
+    use tofino::sim::SimAsic;
+
+    assert_eq!(
+        trace_words(0x10, 0x13, 0x12, 0x11).unwrap(),
+        [0x12, 0x13, 0x10]
+    );
+    assert!(trace_words(0x10, 0x13, 0x14, 0x11).is_err());
+    assert!(Core::parse("33").is_err());
+
+    let map = regs::map::RegMap::new().unwrap();
+    let stall =
+        map.get_offset("eth400g_p7.eth400g_mac.tv80_stall_on_error").unwrap();
+    let halted =
+        map.get_offset("eth400g_p7.eth400g_mac.tv80_halted_status").unwrap();
+    let ctrl =
+        map.get_offset("eth400g_p7.eth400g_mac.tv80_debug_ctrl").unwrap();
+    let head =
+        map.get_offset("eth400g_p7.eth400g_mac.tv80_debug_head_ptr").unwrap();
+    let tail =
+        map.get_offset("eth400g_p7.eth400g_mac.tv80_debug_tail_ptr").unwrap();
+    let window = map.get_offset("eth400g_p7.eth400g_tv80").unwrap();
+    let sim = SimAsic::new();
+    // The core halts as soon as it is stalled.
+    sim.on_write(stall, move |regs, offset, val| {
+        regs.set(offset, val);
+        regs.set(halted, val & 1);
+    });
+    // A trace buffer in words 0x100-0x1ff, holding 2 entries
+    sim.set(ctrl, (1 << 31) | (0x1ff << 12) | 0x100);
+    sim.set(head, 0x1fe);
+    sim.set(tail, 0x100);
+    let ctx = Tofino { map, pci: Box::new(sim) };
+    let core = Core::Mac(7);
+
+    let s = status(&ctx, core).unwrap();
+    assert!(!s.halted && s.trace_enabled);
+    assert_eq!(s.trace_entries, 2);
+    trace(&ctx, core, true, Format::Json).unwrap();
+    assert_eq!(ctx.pci.read4(head).unwrap(), 0x100);
+
+    let dir = tempfile::tempdir().unwrap();
+    let image = dir.path().join("image");
+    std::fs::write(&image, [1, 2, 3, 4, 5, 6]).unwrap();
+    assert!(load(&ctx, core, 0x10, &image, Format::Json).is_err());
+    set_halted(&ctx, core, true).unwrap();
+    ctx.pci.write4(window + 0x14, 0xaabbccdd).unwrap();
+    load(&ctx, core, 0x10, &image, Format::Json).unwrap();
+    assert_eq!(ctx.pci.read4(window + 0x14).unwrap(), 0xaabb0605);
+    assert_eq!(read_window(&ctx, core, 0x11, 2).unwrap(), [2, 3]);
+    set_halted(&ctx, core, false).unwrap();
+    assert!(!status(&ctx, core).unwrap().halted);
+}
@@ -0,0 +1,359 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{SecondsFormat, Utc};
+use regs::map::{Node, RegMap, Register, join_path};
+use serde::Serialize;
+
+use crate::snapshot::{Change, Entry, Snapshot, diff};
+use crate::{Format, Tofino, parse_u64, read_offset};
+
+// Refuse to poll more registers than this, as each pass would take too long
+// to be useful.
+const MAX_WATCHED: usize = 16384;
+
+/// A comparison used by a watch trigger
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    // Longer operators come first, so "<=" isn't mistaken for "<".
+    const ALL: [(&str, Op); 6] = [
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ];
+
+    fn eval(&self, a: u64, b: u64) -> bool {
+        match self {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+        }
+    }
+}
+
+/// A condition of the form "<register>[.<field>] <op> <value>", which ends
+/// the watch once it holds for any of the registers it names.
+#[derive(Debug, PartialEq)]
+struct Trigger {
+    target: String,
+    op: Op,
+    value: u64,
+}
+
+impl Trigger {
+    fn parse(expr: &str) -> Result<Self> {
+        let (pos, op_str, op) = expr
+            .char_indices()
+            .find_map(|(pos, _)| {
+                Op::ALL
+                    .iter()
+                    .find(|(s, _)| expr[pos..].starts_with(s))
+                    .map(|(s, op)| (pos, *s, *op))
+            })
+            .ok_or(anyhow!("no comparison in trigger: {expr}"))?;
+        let target = expr[..pos].trim();
+        if target.is_empty() {
+            bail!("no register in trigger: {expr}");
+        }
+        let value = parse_u64(expr[pos + op_str.len()..].trim())?;
+        Ok(Trigger { target: target.to_string(), op, value })
+    }
+}
+
+// Match a single path component against a pattern, in which '*' matches any
+// run of characters and '?' matches any one character.
+fn glob_match(pat: &[u8], s: &[u8]) -> bool {
+    match (pat.first(), s.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_match(&pat[1..], s)
+                || (!s.is_empty() && glob_match(pat, &s[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_match(&pat[1..], &s[1..]),
+        (Some(p), Some(c)) if p == c => glob_match(&pat[1..], &s[1..]),
+        _ => false,
+    }
+}
+
+// Expand a register path, in which any component may be a glob pattern, into
+// the registers it covers.  A path which names a block or an array covers
+// every register beneath it.
+fn expand(
+    map: &RegMap,
+    path: &str,
+    node: &Node,
+    pattern: &[&str],
+    out: &mut Vec<(String, u32, u32)>,
+) -> Result<()> {
+    let Some((first, rest)) = pattern.split_first() else {
+        return map.walk(path, &mut |path, node| {
+            // Polling a clear-on-read register would destroy the state being
+            // watched.
+            if node.register().unwrap().clears_on_read() {
+                return Ok(());
+            }
+            if out.len() >= MAX_WATCHED {
+                bail!("more than {MAX_WATCHED} registers match");
+            }
+            out.push((path.to_string(), node.offset, node.size / 4));
+            Ok(())
+        });
+    };
+
+    if !first.contains(['*', '?']) {
+        let child = map.get_child(node, first)?;
+        return expand(map, &join_path(path, first), &child, rest, out);
+    }
+    for name in node.children() {
+        if glob_match(first.as_bytes(), name.as_bytes()) {
+            let child = map.get_child(node, &name)?;
+            expand(map, &join_path(path, &name), &child, rest, out)?;
+        }
+    }
+    Ok(())
+}
+
+// Find the registers, and optionally the field, that a trigger refers to.  The
+// target may be a full register path, or a suffix of one or more of the
+// watched registers' paths.  Returns the indices of the watched registers and
+// the field, if any.
+fn resolve_trigger(
+    map: &RegMap,
+    watched: &[(String, u32, u32)],
+    target: &str,
+) -> Result<(Vec<usize>, Option<String>)> {
+    let matching = |reg: &str| {
+        watched
+            .iter()
+            .enumerate()
+            .filter(|(_, (path, _, _))| {
+                path == reg || path.ends_with(&format!(".{reg}"))
+            })
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>()
+    };
+
+    let found = matching(target);
+    if !found.is_empty() {
+        return Ok((found, None));
+    }
+    if let Some((reg, field)) = target.rsplit_once('.') {
+        let found = matching(reg);
+        if let Some(&i) = found.first() {
+            let def = map
+                .get_node(&watched[i].0)?
+                .register()
+                .ok_or(anyhow!("{reg} is not a register"))?;
+            return Ok((found, Some(find_field(def, field)?)));
+        }
+    }
+    bail!("trigger {target} does not name a watched register")
+}
+
+// Find a register's field by its name, or by the tail of its name following
+// an underscore if that is unique: `linkup` finds `macsts_linkup`.
+fn find_field(def: &Register, name: &str) -> Result<String> {
+    if def.fields.iter().any(|f| f.name == name) {
+        return Ok(name.to_string());
+    }
+    let suffix = format!("_{name}");
+    let found: Vec<&str> = def
+        .fields
+        .iter()
+        .map(|f| f.name.as_str())
+        .filter(|f| f.ends_with(&suffix))
+        .collect();
+    match found[..] {
+        [field] => Ok(field.to_string()),
+        [] => bail!("{} has no field {name}", def.name),
+        _ => bail!("{name} is ambiguous in {}: {}", def.name, found.join(", ")),
+    }
+}
+
+/// One poll's worth of changes, in the JSON form of `watch`.  Each poll
+/// that sees a change emits one of these on a line of its own.  If the
+/// trigger fired, `trigger` holds the register and the value that fired it.
+#[derive(Serialize)]
+struct WatchEvent<'a> {
+    time: String,
+    changes: &'a [Change],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trigger: Option<(&'a str, u64)>,
+}
+
+fn poll(ctx: &Tofino, watched: &[(String, u32, u32)]) -> Result<Snapshot> {
+    let mut registers = Vec::with_capacity(watched.len());
+    for (path, offset, words) in watched {
+        let def = ctx.map.get_node(path)?.register();
+        let words = read_offset(ctx, *offset, *words)
+            .with_context(|| format!("reading {path}"))?;
+        let value = match def {
+            Some(def) => def.value(&words)?,
+            None => words[0] as u64,
+        };
+        registers.push(Entry { path: path.clone(), offset: *offset, value });
+    }
+    Ok(Snapshot::new(None, registers))
+}
+
+// Check the trigger against the latest values, returning the register and
+// value that fired it.
+fn check_trigger<'a>(
+    map: &RegMap,
+    trigger: &Trigger,
+    targets: &(Vec<usize>, Option<String>),
+    snap: &'a Snapshot,
+) -> Result<Option<(&'a str, u64)>> {
+    let (indices, field) = targets;
+    for &i in indices {
+        let e = &snap.registers[i];
+        let value = match field {
+            Some(field) => {
+                let node = map.get_node(&e.path)?;
+                let f = node.register().and_then(|def| {
+                    def.fields.iter().find(|f| &f.name == field)
+                });
+                match f {
+                    Some(f) => f.extract(e.value),
+                    None => continue,
+                }
+            }
+            None => e.value,
+        };
+        if trigger.op.eval(value, trigger.value) {
+            return Ok(Some((&e.path, value)));
+        }
+    }
+    Ok(None)
+}
+
+/// Poll the registers matching `pattern` every `interval` milliseconds,
+/// printing each change along with the fields that changed.  If a trigger
+/// is given, stop once it is satisfied.
+pub fn watch(
+    ctx: &Tofino,
+    pattern: &str,
+    interval: u64,
+    until: Option<&str>,
+    format: Format,
+) -> Result<()> {
+    let components: Vec<&str> =
+        pattern.split('.').filter(|c| !c.is_empty()).collect();
+    let mut watched = Vec::new();
+    expand(&ctx.map, "", &ctx.map.root(), &components, &mut watched)?;
+    if watched.is_empty() {
+        bail!("no registers match {pattern}");
+    }
+
+    let trigger = until.map(Trigger::parse).transpose()?;
+    let targets = trigger
+        .as_ref()
+        .map(|t| resolve_trigger(&ctx.map, &watched, &t.target))
+        .transpose()?;
+    if format == Format::Text {
+        println!("watching {} registers", watched.len());
+    }
+
+    let mut prev = poll(ctx, &watched)?;
+    let mut changes = Vec::new();
+    loop {
+        let fired = match (&trigger, &targets) {
+            (Some(t), Some(targets)) => {
+                check_trigger(&ctx.map, t, targets, &prev)?
+            }
+            _ => None,
+        };
+
+        let time = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        if !changes.is_empty() || fired.is_some() {
+            match format {
+                Format::Json => {
+                    let event =
+                        WatchEvent { time, changes: &changes, trigger: fired };
+                    println!("{}", serde_json::to_string(&event)?);
+                }
+                Format::Text => {
+                    for c in &changes {
+                        println!(
+                            "{time} {}: {:#x} -> {:#x}",
+                            c.path,
+                            c.before.unwrap_or_default(),
+                            c.after.unwrap_or_default()
+                        );
+                        for f in &c.fields {
+                            println!(
+                                "{time}     {}: {:#x} -> {:#x}",
+                                f.name, f.before, f.after
+                            );
+                        }
+                    }
+                    if let Some((path, value)) = fired {
+                        println!(
+                            "{time} trigger: {} ({path} = {value:#x})",
+                            until.unwrap()
+                        );
+                    }
+                }
+            }
+        }
+        if fired.is_some() {
+            return Ok(());
+        }
+
+        std::thread::sleep(Duration::from_millis(interval));
+        let cur = poll(ctx, &watched)?;
+        changes = diff(&ctx.map, &prev, &cur);
+        prev = cur;
+    }
+}
+
+#[test]
+fn test_watch_parsing() {
+    assert!(glob_match(b"eth400g_p*", b"eth400g_p12"));
+    assert!(glob_match(b"p?", b"p1"));
+    assert!(!glob_match(b"p?", b"p12"));
+    assert!(glob_match(b"*status*", b"eth_status0"));
+
+    let t = Trigger::parse("eth_status0.linkup != 0xff").unwrap();
+    assert_eq!(
+        t,
+        Trigger {
+            target: "eth_status0.linkup".to_string(),
+            op: Op::Ne,
+            value: 0xff
+        }
+    );
+    let t = Trigger::parse("scratch<=3").unwrap();
+    assert_eq!((t.op, t.value), (Op::Le, 3));
+    assert!(Trigger::parse("scratch 3").is_err());
+
+    // The example given in the help
+    let map = RegMap::new().unwrap();
+    let path = "eth400g_p3.eth400g_mac.eth_status0";
+    let watched = vec![(path.to_string(), map.get_offset(path).unwrap(), 1)];
+    let t = Trigger::parse("eth_status0.linkup != 0xff").unwrap();
+    let (found, field) = resolve_trigger(&map, &watched, &t.target).unwrap();
+    assert_eq!((found, field.as_deref()), (vec![0], Some("macsts_linkup")));
+    assert!(resolve_trigger(&map, &watched, "eth_status0.fault").is_err());
+}
@@ -0,0 +1,18 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+use anyhow::Result;
+
+/// Access to the ASIC's register space.  This is implemented by the
+/// memory-mapped [`crate::pci::Pci`] handle for real hardware, and by
+/// [`crate::sim::SimAsic`] for testing without an ASIC.
+pub trait Backend {
+    /// Read a 4-byte word from the given offset
+    fn read4(&self, offset: u32) -> Result<u32>;
+
+    /// Write a 4-byte word to the given offset
+    fn write4(&self, offset: u32, val: u32) -> Result<()>;
+}
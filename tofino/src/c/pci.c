@@ -3,7 +3,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  *
- * Copyright 2025 Oxide Computer Company
+ * Copyright 2026 Oxide Computer Company
  */
 
 #include <stdio.h>
@@ -44,7 +44,7 @@ pci_open(const char *path)
 }
 
 void *
-pci_map(const char *path, size_t len)
+pci_map(const char *path, size_t len, int *fdp)
 {
 	int fd;
 
@@ -58,12 +58,50 @@ pci_map(const char *path, size_t len)
 	if (base == MAP_FAILED) {
 		snprintf(err_msg, MAX_ERR_LEN,
 		    "failed to map device: %s", strerror(errno));
+		close(fd);
 		return NULL;
 	}
 
+	*fdp = fd;
 	return base;
 }
 
+/*
+ * Map the device for reading only.  The device is not opened exclusively, so
+ * this may be used while another process has it mapped.
+ */
+void *
+pci_map_readonly(const char *path, size_t len, int *fdp)
+{
+	int fd;
+
+	bzero(err_msg, MAX_ERR_LEN + 1);
+	fd = open(path, O_RDONLY);
+	if (fd < 0) {
+		snprintf(err_msg, MAX_ERR_LEN,
+		    "failed to open device: %s", strerror(errno));
+		return NULL;
+	}
+
+	caddr_t base = mmap(NULL, len, PROT_READ, MAP_SHARED, fd, 0);
+	if (base == MAP_FAILED) {
+		snprintf(err_msg, MAX_ERR_LEN,
+		    "failed to map device: %s", strerror(errno));
+		close(fd);
+		return NULL;
+	}
+
+	*fdp = fd;
+	return base;
+}
+
+void
+pci_unmap(void *base, size_t len, int fd)
+{
+	(void) munmap(base, len);
+	(void) close(fd);
+}
+
 int
 pci_check_presence(const char *path)
 {
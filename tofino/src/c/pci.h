@@ -11,7 +11,10 @@
 
 #include <sys/types.h>
 
-extern void *pci_map(const char *path, size_t size);
+extern void *pci_map(const char *path, size_t size, int *fdp);
+extern void *pci_map_readonly(const char *path, size_t size, int *fdp);
+extern void pci_unmap(void *base, size_t size, int fd);
+extern int pci_check_presence(const char *path);
 extern const char *pci_err_msg() ;
 
 #endif
@@ -8,17 +8,20 @@ use std::convert::From;
 use std::fmt;
 
 use anyhow::{Result, anyhow};
+use serde::Serialize;
 
+use crate::backend::Backend;
 use crate::common::get_bits;
-use crate::pci::Pci;
+use crate::{Generation, Stepping};
 
 /// Offset of the first register holding fuse data
 const FUSE_OFFSET: u32 = 0x80180;
 
 /// Number of 4-byte words of fuse data
-const FUSE_SIZE: u32 = 16;
+pub const FUSE_SIZE: u32 = 16;
 
 /// Data stored in the Fuse registers in the Tofino ASIC
+#[derive(Serialize)]
 pub struct Fuse {
     pub device_id: u64,       // 16 bits
     pub version: u64,         // 2 bits
@@ -101,12 +104,18 @@ impl Fuse {
         })
     }
 
-    pub fn read(pci: &Pci) -> Result<Self> {
+    pub fn read(pci: &dyn Backend) -> Result<Self> {
         Self::try_from_slice(&read_raw(pci)?)
     }
+
+    /// Return the generation and stepping identified by the device_id field
+    pub fn model(&self) -> Option<(Generation, Stepping)> {
+        crate::tofino_model(self.device_id as i32)
+    }
 }
 
 /// Parsed version of the chip_id field in the Fuse struct
+#[derive(Serialize)]
 pub struct ChipId {
     pub fab: char,
     pub lot: char,
@@ -171,7 +180,107 @@ impl fmt::Display for ChipId {
     }
 }
 
-pub fn read_raw(pci: &Pci) -> Result<Vec<u32>> {
+/// Number of packet processing pipes, one per bit of pipe_dis
+pub const PIPES: u32 = 4;
+
+/// Width of each pipeN_mau_dis field
+const MAU_DIS_BITS: u32 = 21;
+/// Width of port_dis
+const PORT_DIS_BITS: u32 = 40;
+/// Width of tm_mem_dis
+const TM_MEM_DIS_BITS: u32 = 32;
+
+// The bits set in the low `width` bits of `val`
+fn set_bits(val: u64, width: u32) -> Vec<u32> {
+    (0..width).filter(|b| (val >> b) & 1 == 1).collect()
+}
+
+/// The state of a single pipe, as recorded in the fuse
+#[derive(Serialize)]
+pub struct PipeRestrictions {
+    pub pipe: u32,
+    /// The pipe was disabled in hardware, via pipe_dis
+    pub disabled: bool,
+    /// The pipe was disabled in software, via soft_pipe_dis
+    pub soft_disabled: bool,
+    /// The bits set in the pipe's mau_dis field
+    pub mau_dis: Vec<u32>,
+}
+
+impl PipeRestrictions {
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled && !self.soft_disabled
+    }
+}
+
+/// The fuse's disable and limit fields, grouped by what they restrict.  How
+/// the port_dis, speed_dis, mau_dis and frequency codes map onto MACs,
+/// speeds, stages and clock rates isn't documented, so they are reported as
+/// they are.
+#[derive(Serialize)]
+pub struct Restrictions {
+    pub pipes: Vec<PipeRestrictions>,
+    /// The bits set in port_dis
+    pub port_dis: Vec<u32>,
+    pub speed_dis: u64,
+    pub cpu_speed_dis: u64,
+    /// The core clock limit codes, freq_bps and freq_bps_2
+    pub core_freq: [u64; 2],
+    /// The packets per second clock limit codes, freq_pps and freq_pps_2
+    pub pps_freq: [u64; 2],
+    /// Set if the fuse frequency limits are disabled entirely
+    pub freq_limits_disabled: bool,
+    /// The bits set in tm_mem_dis
+    pub tm_mem_dis: Vec<u32>,
+    /// Names of the optional features that have been disabled
+    pub features_disabled: Vec<&'static str>,
+}
+
+impl From<&Fuse> for Restrictions {
+    fn from(fuse: &Fuse) -> Self {
+        let bit = |val: u64, bit: u32| (val >> bit) & 1 == 1;
+
+        let mau_dis = [
+            fuse.pipe0_mau_dis,
+            fuse.pipe1_mau_dis,
+            fuse.pipe2_mau_dis,
+            fuse.pipe3_mau_dis,
+        ];
+        let pipes = (0..PIPES)
+            .map(|pipe| PipeRestrictions {
+                pipe,
+                disabled: bit(fuse.pipe_dis, pipe),
+                soft_disabled: bit(fuse.soft_pipe_dis, pipe),
+                mau_dis: set_bits(mau_dis[pipe as usize], MAU_DIS_BITS),
+            })
+            .collect();
+
+        let mut features_disabled = Vec::new();
+        if fuse.bsync_dis != 0 {
+            features_disabled.push("bsync");
+        }
+        if fuse.pgen_dis != 0 {
+            features_disabled.push("pgen");
+        }
+        if fuse.resub_dis != 0 {
+            features_disabled.push("resub");
+        }
+
+        Restrictions {
+            pipes,
+            port_dis: set_bits(fuse.port_dis, PORT_DIS_BITS),
+            speed_dis: fuse.speed_dis,
+            cpu_speed_dis: fuse.cpu_speed_dis,
+            core_freq: [fuse.freq_bps, fuse.freq_bps_2],
+            pps_freq: [fuse.freq_pps, fuse.freq_pps_2],
+            freq_limits_disabled: fuse.freq_dis != 0,
+            tm_mem_dis: set_bits(fuse.tm_mem_dis, TM_MEM_DIS_BITS),
+            features_disabled,
+        }
+    }
+}
+
+pub fn read_raw(pci: &dyn Backend) -> Result<Vec<u32>> {
     let mut r = Vec::with_capacity(FUSE_SIZE as usize);
     let mut offset = FUSE_OFFSET;
     for _ in 0..FUSE_SIZE {
@@ -182,6 +291,72 @@ pub fn read_raw(pci: &Pci) -> Result<Vec<u32>> {
     Ok(r)
 }
 
+/// The form of a saved copy of the raw fuse words
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Whitespace- or comma-separated hex words.  `#` starts a comment, and
+    /// anything up to a `:` on each line is treated as an address and
+    /// ignored, so the output of `tftool reg read` may be used as-is.
+    Text,
+    /// The little-endian words back to back
+    Binary,
+}
+
+/// Parse a saved copy of the raw fuse words.
+pub fn parse_dump(data: &[u8], format: DumpFormat) -> Result<Vec<u32>> {
+    if format == DumpFormat::Text {
+        let text = std::str::from_utf8(data)
+            .map_err(|e| anyhow!("text fuse dump is not UTF-8: {e}"))?;
+        return parse_text_dump(text);
+    }
+
+    if data.len() != (FUSE_SIZE * 4) as usize {
+        return Err(anyhow!(
+            "binary fuse dump should be {} bytes.  Found {}",
+            FUSE_SIZE * 4,
+            data.len()
+        ));
+    }
+    Ok(data
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .collect())
+}
+
+fn parse_text_dump(text: &str) -> Result<Vec<u32>> {
+    let mut words = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap();
+        let line = match line.split_once(':') {
+            Some((_addr, val)) => val,
+            None => line,
+        };
+        for word in line.split([' ', '\t', ',']).filter(|w| !w.is_empty()) {
+            let hex = word.strip_prefix("0x").unwrap_or(word);
+            words.push(
+                u32::from_str_radix(hex, 16)
+                    .map_err(|e| anyhow!("invalid fuse word {word}: {e}"))?,
+            );
+        }
+    }
+    if words.len() != FUSE_SIZE as usize {
+        return Err(anyhow!(
+            "fuse should be {FUSE_SIZE} words.  Found {}",
+            words.len()
+        ));
+    }
+    Ok(words)
+}
+
+/// Format the raw fuse words in the text form accepted by [`parse_dump`].
+pub fn format_dump(words: &[u32]) -> String {
+    let mut out = format!("# tofino fuse words at {FUSE_OFFSET:#x}\n");
+    for w in words {
+        out.push_str(&format!("{w:#010x}\n"));
+    }
+    out
+}
+
 #[test]
 fn test_chip() {
     let c = ChipId::from(0x08025dbb797061d4u64);
@@ -197,3 +372,68 @@ fn test_chip() {
     assert_eq!(c.ysign, 0);
     assert_eq!(c.y, 8);
 }
+
+#[test]
+fn test_fuse_read() {
+    use crate::sim::SimAsic;
+
+    let sim = SimAsic::new();
+    // device_id in bits 0-15, part_num in bits 288-301, rev_num in 302-309
+    sim.set(FUSE_OFFSET, 0x0110);
+    sim.set(FUSE_OFFSET + 36, (0x2 << 14) | 0x1234);
+    let fuse = Fuse::read(&sim).unwrap();
+    assert_eq!(fuse.device_id, 0x0110);
+    assert_eq!(fuse.part_num, 0x1234);
+    assert_eq!(fuse.rev_num, 0x2);
+    assert_eq!(fuse.model(), Some((Generation::Tofino2, Stepping::B0)));
+}
+
+#[test]
+fn test_parse_dump() {
+    use DumpFormat::{Binary, Text};
+
+    let words: Vec<u32> = (0..FUSE_SIZE).map(|w| w * 0x01010101).collect();
+
+    let text = format_dump(&words);
+    assert_eq!(parse_dump(text.as_bytes(), Text).unwrap(), words);
+
+    let binary: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    assert_eq!(parse_dump(&binary, Binary).unwrap(), words);
+    assert!(parse_dump(&binary[4..], Binary).is_err());
+
+    // The multi-word output of "tftool reg read"
+    let reg_read: String = words
+        .iter()
+        .enumerate()
+        .map(|(i, w)| format!("{:x}: {w:x}\n", FUSE_OFFSET + 4 * i as u32))
+        .collect();
+    assert_eq!(parse_dump(reg_read.as_bytes(), Text).unwrap(), words);
+
+    assert!(parse_dump(b"0x1 0x2", Text).is_err());
+    // 64 bytes of malformed text are not mistaken for a binary dump.
+    let malformed = [b'z'; (FUSE_SIZE * 4) as usize];
+    assert!(parse_dump(&malformed, Text).is_err());
+}
+
+#[test]
+fn test_restrictions() {
+    let mut data = [0u32; FUSE_SIZE as usize];
+    // speed_dis starts at bit 27
+    data[0] = 0x2 << 29;
+    // port_dis starts at bit 91: set its bits 3 and 39
+    data[2] = 1 << (91 - 64 + 3);
+    data[4] = 1 << (91 + 39 - 128);
+    // pipe_dis starts at bit 131: disable pipe 1
+    data[4] |= 0x2 << 3;
+    // pipe0_mau_dis starts at bit 135: set its last bit, 20
+    data[4] |= 1 << (135 - 128 + 20);
+
+    let r = Restrictions::from(&Fuse::try_from_slice(&data).unwrap());
+    assert!(r.pipes[0].is_enabled());
+    assert_eq!(r.pipes[0].mau_dis, [20]);
+    assert!(!r.pipes[1].is_enabled());
+    assert!(r.pipes[2].mau_dis.is_empty());
+    assert_eq!(r.port_dis, [3, 39]);
+    assert_eq!(r.speed_dis, 0x2 << 2);
+    assert_eq!(r.core_freq, [0, 0]);
+}
@@ -4,25 +4,104 @@
 
 // Copyright 2023 Oxide Computer Company
 
-use anyhow::{Error, Result};
+use anyhow::{Error, Result, anyhow};
+use serde::Serialize;
 
+pub mod backend;
 pub mod common;
 pub mod fuse;
 pub mod pci;
+pub mod pll;
+pub mod port;
+pub mod pvt;
+pub mod reset;
+pub mod sim;
+pub mod spi;
 
 pub const REGISTER_SIZE: usize = 72 * 1024 * 1024;
 
-#[derive(Clone, Debug, PartialEq)]
+/// The generation of a Tofino ASIC
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Generation {
+    Tofino1,
+    Tofino2,
+}
+
+impl std::fmt::Display for Generation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Generation::Tofino1 => write!(f, "TF1"),
+            Generation::Tofino2 => write!(f, "TF2"),
+        }
+    }
+}
+
+/// The silicon stepping of a Tofino ASIC
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Stepping {
+    A0,
+    A00,
+    B0,
+}
+
+impl std::fmt::Display for Stepping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stepping::A0 => write!(f, "A0"),
+            Stepping::A00 => write!(f, "A00"),
+            Stepping::B0 => write!(f, "B0"),
+        }
+    }
+}
+
+const TOFINO_SUBSYSTEM_VID: i32 = 0x1d1c;
+const TOFINO_SUBSYSTEM_ID: [(i32, Generation, Stepping); 5] = [
+    (0x0001, Generation::Tofino1, Stepping::A0),
+    (0x0010, Generation::Tofino1, Stepping::B0),
+    (0x0100, Generation::Tofino2, Stepping::A0),
+    (0x0000, Generation::Tofino2, Stepping::A00),
+    (0x0110, Generation::Tofino2, Stepping::B0),
+];
+
+/// Return the generation and stepping of the tofino model with the given
+/// subsystem ID.  The same IDs are burned into the fuse's device_id field.
+pub fn tofino_model(id: i32) -> Option<(Generation, Stepping)> {
+    TOFINO_SUBSYSTEM_ID
+        .iter()
+        .find(|(i, _, _)| *i == id)
+        .map(|(_, g, s)| (*g, *s))
+}
+
+// Determine whether a PCI vendor/subsystem ID pair represents one of the
+// tofino models we support.
+#[cfg_attr(
+    not(any(target_os = "illumos", target_os = "linux")),
+    allow(dead_code)
+)]
+fn is_tofino_id(vid: i32, id: i32) -> bool {
+    vid == TOFINO_SUBSYSTEM_VID && tofino_model(id).is_some()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct TofinoNode {
     pub name: String,
     pub driver: Option<String>,
+    /// The driver instance on illumos.  On Linux, the asic's PCI address,
+    /// as `domain << 16 | bus << 8 | device << 3 | function`.
     pub instance: Option<i32>,
     pub available: bool,
     pub devfs_path: String,
+    pub subsystem_id: i32,
+    pub generation: Generation,
+    pub stepping: Stepping,
+    /// The unique chip ID burned into the fuse.  Discovery doesn't open the
+    /// asic, so this is only known where the fuse can be read through a
+    /// shared, read-only mapping: on Linux, through sysfs.
+    pub chip_id: Option<u64>,
 }
 
 impl TofinoNode {
-    /// Return the /dev path to the tofino device
+    /// Return the path to the device used to map the tofino registers
     pub fn device_path(&self) -> Result<String, Error> {
         plat::device_path(self)
     }
@@ -41,6 +120,31 @@ impl TofinoNode {
     pub fn open_pci(&self) -> Result<pci::Pci> {
         pci::Pci::new(&plat::device_path(self)?, REGISTER_SIZE)
     }
+
+    /// Verify that the model identified by the fuse data matches the model
+    /// identified by the PCI subsystem ID.  The fuse's rev_num and part_num
+    /// are included in any mismatch report, to help identify the part.
+    pub fn check_fuse(&self, fuse: &fuse::Fuse) -> Result<()> {
+        let model = (self.generation, self.stepping);
+        match fuse.model() {
+            Some(fused) if fused == model => Ok(()),
+            fused => Err(anyhow!(
+                "PCI subsystem ID {:#06x} identifies a {} {}, but fuse \
+                 device_id {:#06x} identifies {} (rev_num {:#x}, \
+                 part_num {:#x})",
+                self.subsystem_id,
+                self.generation,
+                self.stepping,
+                fuse.device_id,
+                match fused {
+                    Some((g, s)) => format!("a {g} {s}"),
+                    None => "an unknown model".to_string(),
+                },
+                fuse.rev_num,
+                fuse.part_num,
+            )),
+        }
+    }
 }
 
 #[cfg(target_os = "illumos")]
@@ -51,57 +155,40 @@ mod plat {
     use anyhow::{Context, Error, Result, anyhow, bail};
     use illumos_devinfo::DevInfo;
 
-    const TOFINO_SUBSYSTEM_VID: i32 = 0x1d1c;
-    const TOFINO_SUBSYSTEM_ID: [i32; 5] = [
-        0x0001, // TF1_A0
-        0x0010, // TF1_B0
-        0x0100, // TF2_A0
-        0x0000, // TF2_A00
-        0x0110, // TF2_B0
-    ];
-
     // Given a node name from the devinfo snapshot, determine whether it
-    // represents one of the tofino models we support.
-    fn is_tofino_node_name(name: &str) -> bool {
-        if let Some(pci) = name.strip_prefix("pci")
-            && let Some((vid, id)) = pci.split_once(',')
-        {
-            let vid = match i32::from_str_radix(vid, 16) {
-                Ok(v) => v,
-                Err(_) => return false,
-            };
-            let id = match i32::from_str_radix(id, 16) {
-                Ok(i) => i,
-                Err(_) => return false,
-            };
+    // represents one of the tofino models we support.  If so, return its
+    // subsystem ID.
+    fn tofino_node_id(name: &str) -> Option<i32> {
+        let pci = name.strip_prefix("pci")?;
+        let (vid, id) = pci.split_once(',')?;
+        let vid = i32::from_str_radix(vid, 16).ok()?;
+        let id = i32::from_str_radix(id, 16).ok()?;
 
-            return vid == TOFINO_SUBSYSTEM_VID
-                && TOFINO_SUBSYSTEM_ID.contains(&id);
-        }
-
-        false
+        crate::is_tofino_id(vid, id).then_some(id)
     }
 
-    // Load the devinfo map, and scan it for any node representing a tofino asic.
-    // TODO-completeness: We could return a vector containing all the asics.  Since
-    // we know that our current platform can have no more than one, we stop scanning
-    // as soon as we find that one.
+    // Load the devinfo map, and scan it for all nodes representing a tofino
+    // asic.
     pub fn get_tofino_nodes() -> Result<Vec<crate::TofinoNode>> {
         let mut device_info =
             DevInfo::new_force_load().with_context(|| "loading devinfo map")?;
         get_tofino_nodes_from(&mut device_info)
     }
 
+    // The asics are returned in order of their instance numbers.
     pub fn get_tofino_nodes_from(
         device_info: &mut DevInfo,
     ) -> Result<Vec<crate::TofinoNode>> {
+        let mut nodes = Vec::new();
         let mut node_walker = device_info.walk_node();
         while let Some(node) = node_walker
             .next()
             .transpose()
             .map_err(|e| anyhow!("unable to walk device tree: {:?}", e))?
         {
-            if is_tofino_node_name(&node.node_name()) {
+            if let Some(subsystem_id) = tofino_node_id(&node.node_name()) {
+                let (generation, stepping) =
+                    crate::tofino_model(subsystem_id).unwrap();
                 let available = match node.instance() {
                     Some(i) => {
                         let path = format!("/dev/tofino/{i}");
@@ -109,16 +196,22 @@ mod plat {
                     }
                     None => false,
                 };
-                return Ok(vec![crate::TofinoNode {
+                let tofino = crate::TofinoNode {
                     name: node.node_name(),
                     driver: node.driver_name(),
                     instance: node.instance(),
                     available,
                     devfs_path: node.devfs_path()?,
-                }]);
+                    subsystem_id,
+                    generation,
+                    stepping,
+                    chip_id: None,
+                };
+                nodes.push(tofino);
             }
         }
-        Ok(Vec::new())
+        nodes.sort_by_key(|n| n.instance);
+        Ok(nodes)
     }
 
     fn is_char_device(name: impl Into<PathBuf>) -> bool {
@@ -146,7 +239,213 @@ mod plat {
     }
 }
 
-#[cfg(not(target_os = "illumos"))]
+#[cfg(target_os = "linux")]
+mod plat {
+    use std::path::{Path, PathBuf};
+
+    use crate::pci;
+    use anyhow::{Context, Error, Result, bail};
+
+    const SYSFS_ROOT: &str = "/sys";
+    const PCI_DEVICES: &str = "bus/pci/devices";
+
+    // BAR0 contains the config/status registers
+    const BAR0_RESOURCE: &str = "resource0";
+
+    // Read a sysfs attribute containing a single hex value (e.g., "0x1d1c")
+    fn read_hex_attr(dev: &Path, attr: &str) -> Option<i32> {
+        let val = std::fs::read_to_string(dev.join(attr)).ok()?;
+        let val = val.trim();
+        i32::from_str_radix(val.strip_prefix("0x").unwrap_or(val), 16).ok()
+    }
+
+    // Return the vendor and subsystem IDs of a PCI device.  As in the illumos
+    // devinfo node name, we use the subsystem IDs if the device has them, and
+    // fall back to the vendor/device IDs if it doesn't.
+    fn device_ids(dev: &Path) -> Option<(i32, i32)> {
+        match read_hex_attr(dev, "subsystem_vendor") {
+            Some(vid) if vid != 0 => {
+                Some((vid, read_hex_attr(dev, "subsystem_device")?))
+            }
+            _ => Some((
+                read_hex_attr(dev, "vendor")?,
+                read_hex_attr(dev, "device")?,
+            )),
+        }
+    }
+
+    // If the device is bound to a UIO driver, return the uio device number.
+    fn uio_instance(dev: &Path) -> Option<i32> {
+        std::fs::read_dir(dev.join("uio"))
+            .ok()?
+            .filter_map(|e| e.ok())
+            .find_map(|e| {
+                e.file_name().to_str()?.strip_prefix("uio")?.parse().ok()
+            })
+    }
+
+    // Scan the live sysfs tree for any PCI device representing a tofino asic.
+    pub fn get_tofino_nodes() -> Result<Vec<crate::TofinoNode>> {
+        get_tofino_nodes_from(Path::new(SYSFS_ROOT))
+    }
+
+    // Derive an instance number from a PCI address of the form
+    // "domain:bus:device.function".  Addresses in a domain too large for the
+    // instance number to hold are rejected.
+    fn bdf_instance(addr: &str) -> Option<i32> {
+        let hex = |s: &str, limit: u32| {
+            u32::from_str_radix(s, 16).ok().filter(|v| *v < limit)
+        };
+        let (domain, rest) = addr.split_once(':')?;
+        let (bus, rest) = rest.split_once(':')?;
+        let (device, function) = rest.split_once('.')?;
+        let bdf = hex(domain, 1 << 16)? << 16
+            | hex(bus, 1 << 8)? << 8
+            | hex(device, 1 << 5)? << 3
+            | hex(function, 1 << 3)?;
+        i32::try_from(bdf).ok()
+    }
+
+    // Read the chip ID from the fuse through a shared, read-only mapping of
+    // BAR0, which is available whether or not the asic is in use.  The fuse
+    // layout we know how to decode is specific to the Tofino 2.
+    fn read_chip_id(dev: &Path, generation: crate::Generation) -> Option<u64> {
+        if generation != crate::Generation::Tofino2 {
+            return None;
+        }
+        let bar = dev.join(BAR0_RESOURCE);
+        let pci =
+            pci::Pci::new_readonly(bar.to_str()?, crate::REGISTER_SIZE).ok()?;
+        crate::fuse::Fuse::read(&pci).ok().map(|f| f.chip_id)
+    }
+
+    // Scan the sysfs tree rooted at the given path.  The devices are returned
+    // in order of their PCI addresses, from which their instance numbers are
+    // derived.
+    pub fn get_tofino_nodes_from(
+        sysfs: &Path,
+    ) -> Result<Vec<crate::TofinoNode>> {
+        let dir = sysfs.join(PCI_DEVICES);
+        let mut devices = std::fs::read_dir(&dir)
+            .with_context(|| format!("reading {}", dir.display()))?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()
+            .with_context(|| format!("reading {}", dir.display()))?;
+        devices.sort();
+
+        let mut nodes = Vec::new();
+        for dev in devices {
+            let Some((vid, id)) = device_ids(&dev) else {
+                continue;
+            };
+            if !crate::is_tofino_id(vid, id) {
+                continue;
+            }
+            let (generation, stepping) = crate::tofino_model(id).unwrap();
+
+            let driver = std::fs::read_link(dev.join("driver"))
+                .ok()
+                .and_then(|d| Some(d.file_name()?.to_string_lossy().into()));
+            let instance = dev
+                .file_name()
+                .and_then(|addr| bdf_instance(&addr.to_string_lossy()));
+            let mut node = crate::TofinoNode {
+                name: format!("pci{vid:x},{id:x}"),
+                driver,
+                instance,
+                available: false,
+                devfs_path: dev.to_string_lossy().into(),
+                subsystem_id: id,
+                generation,
+                stepping,
+                chip_id: read_chip_id(&dev, generation),
+            };
+            node.available = match device_path(&node) {
+                Ok(path) => pci::Pci::check_presence(&path),
+                Err(_) => false,
+            };
+            nodes.push(node);
+        }
+        Ok(nodes)
+    }
+
+    // If the asic is bound to a UIO driver, its registers are mapped through
+    // the /dev/uioN device.  Otherwise we map BAR0 directly through sysfs.
+    pub fn device_path(node: &crate::TofinoNode) -> Result<String, Error> {
+        if node.instance.is_none() {
+            bail!("no tofino present");
+        }
+
+        let dev = Path::new(&node.devfs_path);
+        let path = match uio_instance(dev) {
+            Some(uio) => PathBuf::from(format!("/dev/uio{uio}")),
+            None => dev.join(BAR0_RESOURCE),
+        };
+        if !path.exists() {
+            bail!("{} is not a valid device", path.display());
+        }
+
+        Ok(path.to_string_lossy().into())
+    }
+
+    #[cfg(test)]
+    fn fake_device(sysfs: &Path, addr: &str, ids: [&str; 4]) -> PathBuf {
+        let dev = sysfs.join(PCI_DEVICES).join(addr);
+        std::fs::create_dir_all(&dev).unwrap();
+        let attrs =
+            ["vendor", "device", "subsystem_vendor", "subsystem_device"];
+        for (attr, val) in attrs.iter().zip(ids) {
+            std::fs::write(dev.join(attr), format!("{val}\n")).unwrap();
+        }
+        // A sparse file standing in for the register BAR
+        std::fs::File::create(dev.join(BAR0_RESOURCE))
+            .unwrap()
+            .set_len(crate::REGISTER_SIZE as u64)
+            .unwrap();
+        dev
+    }
+
+    #[test]
+    fn test_sysfs_discovery() {
+        let sysfs = tempfile::tempdir().unwrap();
+        let root = sysfs.path();
+        fake_device(root, "0000:00:1f.0", ["0x8086", "0xa0a4", "0x0", "0x0"]);
+        let tf2 = fake_device(
+            root,
+            "0000:05:00.0",
+            ["0x1d1c", "0x0110", "0x1d1c", "0x0110"],
+        );
+        // A tofino-branded device with an unsupported subsystem ID
+        fake_device(
+            root,
+            "0000:06:00.0",
+            ["0x1d1c", "0x0110", "0x1d1c", "0x9"],
+        );
+
+        let nodes = get_tofino_nodes_from(root).unwrap();
+        assert_eq!(nodes.len(), 1);
+        let node = &nodes[0];
+        assert_eq!(node.name, "pci1d1c,110");
+        assert_eq!(node.instance, Some(0x500));
+        assert_eq!(node.driver, None);
+        assert_eq!(node.subsystem_id, 0x0110);
+        assert_eq!(node.generation, crate::Generation::Tofino2);
+        assert_eq!(node.stepping, crate::Stepping::B0);
+        assert!(node.available);
+        assert_eq!(node.chip_id, Some(0));
+        assert_eq!(node.devfs_path, tf2.to_string_lossy());
+        assert_eq!(
+            device_path(node).unwrap(),
+            tf2.join(BAR0_RESOURCE).to_string_lossy()
+        );
+
+        assert_eq!(bdf_instance("7fff:ff:1f.7"), Some(0x7fff_ffff));
+        assert_eq!(bdf_instance("8000:05:00.0"), None);
+        assert_eq!(bdf_instance("0000:05:20.0"), None);
+    }
+}
+
+#[cfg(not(any(target_os = "illumos", target_os = "linux")))]
 mod plat {
     use anyhow::Error;
     use anyhow::Result;
@@ -169,6 +468,19 @@ pub fn get_tofino_from_devinfo(
     Ok(all.pop())
 }
 
+#[cfg(target_os = "linux")]
+pub fn get_tofino_from_sysfs(
+    sysfs: &std::path::Path,
+) -> Result<Option<TofinoNode>> {
+    let mut all = plat::get_tofino_nodes_from(sysfs)?;
+    Ok(all.pop())
+}
+
+/// Return all of the tofino asics on this system, ordered by instance
+pub fn get_tofino_nodes() -> Result<Vec<TofinoNode>> {
+    plat::get_tofino_nodes()
+}
+
 pub fn get_tofino() -> Result<Option<TofinoNode>> {
     let mut all = plat::get_tofino_nodes()?;
     Ok(all.pop())
@@ -9,11 +9,24 @@
 use anyhow::{Result, anyhow};
 use std::ffi::{CStr, CString};
 
+use crate::backend::Backend;
+
 unsafe extern "C" {
     pub fn pci_map(
         path: *const ::std::os::raw::c_char,
         size: usize,
+        fdp: *mut ::core::ffi::c_int,
     ) -> *mut ::core::ffi::c_void;
+    pub fn pci_map_readonly(
+        path: *const ::std::os::raw::c_char,
+        size: usize,
+        fdp: *mut ::core::ffi::c_int,
+    ) -> *mut ::core::ffi::c_void;
+    pub fn pci_unmap(
+        base: *mut ::core::ffi::c_void,
+        size: usize,
+        fd: ::core::ffi::c_int,
+    );
     pub fn pci_check_presence(
         path: *const ::std::os::raw::c_char,
     ) -> ::core::ffi::c_int;
@@ -24,14 +37,31 @@ unsafe extern "C" {
 pub struct Pci {
     ptr: *mut ::core::ffi::c_void,
     len: usize,
+    fd: ::core::ffi::c_int,
+    writable: bool,
 }
 
 impl Pci {
     /// Open the ASIC and map the BAR containing the config/status registers.
     pub fn new(path: &str, len: usize) -> Result<Self> {
+        Self::map(path, len, true)
+    }
+
+    /// Map the BAR containing the config/status registers for reading only.
+    /// The ASIC is not opened exclusively, so this doesn't interfere with
+    /// another process using it.
+    pub fn new_readonly(path: &str, len: usize) -> Result<Self> {
+        Self::map(path, len, false)
+    }
+
+    fn map(path: &str, len: usize, writable: bool) -> Result<Self> {
+        let mut fd = -1;
         let ptr = unsafe {
             let path = CString::new(path).unwrap();
-            pci_map(path.as_ptr(), len)
+            match writable {
+                true => pci_map(path.as_ptr(), len, &mut fd),
+                false => pci_map_readonly(path.as_ptr(), len, &mut fd),
+            }
         };
 
         if ptr.is_null() {
@@ -40,7 +70,7 @@ impl Pci {
             };
             Err(anyhow!("failed to map {}: {}", path, msg))
         } else {
-            Ok(Pci { ptr, len })
+            Ok(Pci { ptr, len, fd, writable })
         }
     }
 
@@ -73,6 +103,9 @@ impl Pci {
 
     /// Write a 4-byte word to the given offset
     pub fn write4(&self, offset: u32, val: u32) -> Result<()> {
+        if !self.writable {
+            return Err(anyhow!("the device is mapped read-only"));
+        }
         let ptr = self.get_word_ptr(offset)?;
         unsafe {
             std::ptr::write(ptr, val);
@@ -80,3 +113,21 @@ impl Pci {
         Ok(())
     }
 }
+
+impl Drop for Pci {
+    /// Unmap the registers and close the device, allowing it to be opened
+    /// again.
+    fn drop(&mut self) {
+        unsafe { pci_unmap(self.ptr, self.len, self.fd) }
+    }
+}
+
+impl Backend for Pci {
+    fn read4(&self, offset: u32) -> Result<u32> {
+        Pci::read4(self, offset)
+    }
+
+    fn write4(&self, offset: u32, val: u32) -> Result<()> {
+        Pci::write4(self, offset, val)
+    }
+}
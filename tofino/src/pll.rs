@@ -0,0 +1,112 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+//! The state of the clock PLLs configured through misc_regs.  Each PLL is
+//! controlled by a pair of registers, which tf2.rsf describes only as opaque
+//! 32-bit fields.  Without a layout for them, the dividers, bypass and reset
+//! state can't be decoded, nor the output frequency computed and checked
+//! against the fuse's freq_bps and freq_pps limits, so the registers are
+//! reported as they are.  Loss of lock is latched in misc_intr.stat, in the
+//! *_pll_lol fields.
+
+use std::fmt;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::backend::Backend;
+
+/// Offset of misc_regs.misc_intr.stat, holding the loss-of-lock bits
+const MISC_INTR_STAT: u32 = 0x80200;
+
+/// One of the PLLs in misc_regs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pll {
+    Core,
+    Pps,
+    Mac0,
+    Mac1,
+}
+
+impl Pll {
+    pub const ALL: [Pll; 4] = [Pll::Core, Pll::Pps, Pll::Mac0, Pll::Mac1];
+
+    /// Offset of the PLL's ctrl0 register, which ctrl1 follows
+    fn ctrl_offset(&self) -> u32 {
+        match self {
+            Pll::Pps => 0x80018,
+            Pll::Core => 0x80020,
+            Pll::Mac0 => 0x80028,
+            Pll::Mac1 => 0x80030,
+        }
+    }
+
+    /// The bit in misc_intr.stat latching loss of lock
+    fn lol_bit(&self) -> u32 {
+        match self {
+            Pll::Core => 4,
+            Pll::Pps => 5,
+            Pll::Mac0 => 6,
+            Pll::Mac1 => 7,
+        }
+    }
+}
+
+impl fmt::Display for Pll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Pll::Core => "core",
+            Pll::Pps => "pps",
+            Pll::Mac0 => "mac0",
+            Pll::Mac1 => "mac1",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// The state of a single PLL
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PllStatus {
+    pub pll: Pll,
+    /// The contents of ctrl0 and ctrl1
+    pub ctrl: [u32; 2],
+    /// Loss of lock has been latched since the bit was last cleared
+    pub lost_lock: bool,
+}
+
+/// Read the state of every PLL.
+pub fn read_all(pci: &dyn Backend) -> Result<Vec<PllStatus>> {
+    let stat = pci.read4(MISC_INTR_STAT)?;
+    let mut all = Vec::new();
+    for pll in Pll::ALL {
+        let offset = pll.ctrl_offset();
+        all.push(PllStatus {
+            pll,
+            ctrl: [pci.read4(offset)?, pci.read4(offset + 4)?],
+            lost_lock: (stat >> pll.lol_bit()) & 1 != 0,
+        });
+    }
+    Ok(all)
+}
+
+#[test]
+fn test_pll_read() {
+    use crate::sim::SimAsic;
+
+    let sim = SimAsic::new();
+    sim.set(0x80020, 0x1234);
+    sim.set(0x80024, 0x5678);
+    sim.set(MISC_INTR_STAT, 1 << 7);
+
+    let all = read_all(&sim).unwrap();
+    let core = &all[0];
+    assert_eq!(core.pll, Pll::Core);
+    assert_eq!(core.ctrl, [0x1234, 0x5678]);
+    assert!(!core.lost_lock);
+    assert_eq!(all[3].pll, Pll::Mac1);
+    assert!(all[3].lost_lock);
+}
@@ -0,0 +1,235 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+//! The mapping from a board's front-panel ports to the MACs behind them.
+//! Each eth400g MAC drives 8 serdes lanes, which may be split among as many
+//! as 8 logical ports, and the CPU MAC drives 4.  A port's channel within
+//! its MAC is the first of its lanes.
+//!
+//! How the connectors are wired to the MACs varies from board to board, so
+//! the map may be loaded from a file.  Each line describes one port, as its
+//! name, the connector it belongs to (or `-`), its MAC and its lanes:
+//!
+//! ```text
+//! # port  connector  mac  lanes
+//! 1/0     qsfp1      5    0-3
+//! 1/1     qsfp1      5    4-7
+//! cpu     -          0    0-3
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
+
+/// The number of MACs: MAC 0 is the eth100g CPU MAC, and MACs 1-32 are the
+/// eth400g front-panel MACs.
+pub const MACS: u32 = 33;
+/// The number of lanes driven by the CPU MAC
+const CPU_LANES: u32 = 4;
+/// The number of lanes driven by each eth400g MAC
+const MAC_LANES: u32 = 8;
+
+/// The number of lanes driven by a MAC
+pub fn mac_lanes(mac: u32) -> u32 {
+    match mac {
+        0 => CPU_LANES,
+        _ => MAC_LANES,
+    }
+}
+
+/// A logical port, and the lanes of the MAC carrying it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Port {
+    pub name: String,
+    /// The front-panel connector the port belongs to, if any
+    pub connector: Option<String>,
+    pub mac: u32,
+    pub first_lane: u32,
+    pub lane_count: u32,
+}
+
+impl Port {
+    pub fn lanes(&self) -> Range<u32> {
+        self.first_lane..self.first_lane + self.lane_count
+    }
+
+    /// The port's channel within its MAC
+    pub fn channel(&self) -> u32 {
+        self.first_lane
+    }
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (mac {} lanes ", self.name, self.mac)?;
+        match self.lane_count {
+            1 => write!(f, "{})", self.first_lane),
+            _ => write!(f, "{}-{})", self.first_lane, self.lanes().end - 1),
+        }
+    }
+}
+
+// Parse a lane range, either a single lane or of the form "<first>-<last>".
+fn parse_lanes(s: &str) -> Result<Range<u32>> {
+    let (first, last) = s.split_once('-').unwrap_or((s, s));
+    let first: u32 = first.parse()?;
+    let last: u32 = last.parse()?;
+    if last < first {
+        bail!("lanes {s} are backwards");
+    }
+    Ok(first..last + 1)
+}
+
+/// A board's front-panel ports
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortMap {
+    ports: Vec<Port>,
+}
+
+impl Default for PortMap {
+    /// Every MAC carries a single port, named for the MAC and occupying all
+    /// of its lanes.  The eth400g MACs each have a connector of their own.
+    fn default() -> Self {
+        let ports = (0..MACS)
+            .map(|mac| Port {
+                name: match mac {
+                    0 => "cpu".to_string(),
+                    _ => mac.to_string(),
+                },
+                connector: (mac > 0).then(|| format!("qsfp{mac}")),
+                mac,
+                first_lane: 0,
+                lane_count: mac_lanes(mac),
+            })
+            .collect();
+        PortMap { ports }
+    }
+}
+
+impl PortMap {
+    /// Parse a board's port map, in the form described above.  `#` starts
+    /// a comment.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut ports: Vec<Port> = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            let port = Self::parse_port(&words)
+                .with_context(|| format!("line {}: {}", n + 1, line.trim()))?;
+            if let Some(p) = ports.iter().find(|p| {
+                p.name == port.name
+                    || (p.mac == port.mac
+                        && p.lanes().start < port.lanes().end
+                        && port.lanes().start < p.lanes().end)
+            }) {
+                bail!("line {}: port {port} conflicts with {p}", n + 1);
+            }
+            ports.push(port);
+        }
+        Ok(PortMap { ports })
+    }
+
+    fn parse_port(words: &[&str]) -> Result<Port> {
+        let [name, connector, mac, lanes] = words else {
+            bail!("expected a port, connector, MAC and lanes");
+        };
+        let mac: u32 = mac.parse().context("invalid MAC")?;
+        if mac >= MACS {
+            bail!("no such MAC: {mac}");
+        }
+        let lanes = parse_lanes(lanes).context("invalid lanes")?;
+        let count = lanes.len() as u32;
+        // A port's lanes must be a naturally aligned power of two.
+        if lanes.end > mac_lanes(mac)
+            || !count.is_power_of_two()
+            || !lanes.start.is_multiple_of(count)
+        {
+            bail!("MAC {mac} can't carry a port on lanes {lanes:?}");
+        }
+        Ok(Port {
+            name: name.to_string(),
+            connector: (*connector != "-").then(|| connector.to_string()),
+            mac,
+            first_lane: lanes.start,
+            lane_count: count,
+        })
+    }
+
+    /// Load a board's port map from a file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&text)
+            .with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn ports(&self) -> &[Port] {
+        &self.ports
+    }
+
+    /// Return the port with the given name, or every port of the connector
+    /// with that name.
+    pub fn find(&self, name: &str) -> Result<Vec<&Port>> {
+        if let Some(port) = self.ports.iter().find(|p| p.name == name) {
+            return Ok(vec![port]);
+        }
+        let ports: Vec<&Port> = self
+            .ports
+            .iter()
+            .filter(|p| p.connector.as_deref() == Some(name))
+            .collect();
+        match ports.is_empty() {
+            true => Err(anyhow!("no such port or connector: {name}")),
+            false => Ok(ports),
+        }
+    }
+}
+
+#[test]
+fn test_port_map() {
+    let default = PortMap::default();
+    assert_eq!(default.ports().len(), MACS as usize);
+    let cpu = &default.find("cpu").unwrap()[0];
+    assert_eq!((cpu.mac, cpu.lanes()), (0, 0..4));
+    assert_eq!(default.find("qsfp7").unwrap()[0].name, "7");
+
+    let map = PortMap::parse(
+        "# port  connector  mac  lanes\n\
+         1/0     qsfp1      5    0-3\n\
+         1/1     qsfp1      5    4-5  # a 2-lane breakout\n\
+         1/2     qsfp1      5    6\n\
+         \n\
+         2       qsfp2      3    0-7\n\
+         cpu     -          0    0-3\n",
+    )
+    .unwrap();
+    let ports = map.find("qsfp1").unwrap();
+    let names: Vec<&str> = ports.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["1/0", "1/1", "1/2"]);
+    assert_eq!(ports[1].channel(), 4);
+    assert_eq!(ports[2].to_string(), "1/2 (mac 5 lanes 6)");
+    assert_eq!(map.find("cpu").unwrap()[0].connector, None);
+    assert!(map.find("qsfp3").is_err());
+
+    for bad in [
+        "1 - 5",
+        "1 - 33 0-7",
+        "1 - 0 0-7",
+        "1 - 5 1-2",
+        "1 - 5 0-2",
+        "1 - 5 3-2",
+        "1 - 5 0-3\n2 - 5 2-3",
+        "1 - 5 0-3\n1 - 6 0-3",
+    ] {
+        assert!(PortMap::parse(bad).is_err(), "accepted {bad:?}");
+    }
+}
@@ -0,0 +1,74 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+//! The state of the process/voltage/temperature sensor in misc_regs.
+//! tf2.rsf describes pvt_ctrl only as a single 24-bit field, so there is no
+//! documented way to select a measurement or trigger a conversion, and it
+//! gives no conversion from the sensor's 10-bit codes to degrees Celsius or
+//! millivolts.  The sensor is therefore never reconfigured or triggered, and
+//! whatever conversion it last completed is reported as a raw code.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::backend::Backend;
+
+/// Offset of misc_regs.pvt_ctrl
+const PVT_CTRL: u32 = 0x801f4;
+/// Offset of misc_regs.pvt_int, holding the alarm thresholds
+const PVT_INT: u32 = 0x801f8;
+/// Offset of misc_regs.pvt_status
+const PVT_STATUS: u32 = 0x801fc;
+
+/// pvt_status.pvt_status, and pvt_int.pvt_lo_thr and pvt_hi_thr
+const CODE_MASK: u32 = 0x3ff;
+/// pvt_status.val_status
+const STATUS_VALID: u32 = 1 << 12;
+const THRESHOLD_LO_SHIFT: u32 = 0;
+const THRESHOLD_HI_SHIFT: u32 = 16;
+
+/// The contents of the sensor's registers
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Pvt {
+    /// pvt_ctrl, which selects what the sensor is measuring
+    pub ctrl: u32,
+    /// The code produced by the last conversion
+    pub code: u32,
+    /// Set if `code` holds a completed conversion
+    pub valid: bool,
+    /// The codes below and above which the sensor raises an alarm
+    pub alarm_lo: u32,
+    pub alarm_hi: u32,
+}
+
+impl Pvt {
+    pub fn read(pci: &dyn Backend) -> Result<Self> {
+        let status = pci.read4(PVT_STATUS)?;
+        let thresholds = pci.read4(PVT_INT)?;
+        Ok(Pvt {
+            ctrl: pci.read4(PVT_CTRL)?,
+            code: status & CODE_MASK,
+            valid: status & STATUS_VALID != 0,
+            alarm_lo: (thresholds >> THRESHOLD_LO_SHIFT) & CODE_MASK,
+            alarm_hi: (thresholds >> THRESHOLD_HI_SHIFT) & CODE_MASK,
+        })
+    }
+}
+
+#[test]
+fn test_pvt_read() {
+    use crate::sim::SimAsic;
+
+    let sim = SimAsic::new();
+    sim.set(PVT_CTRL, 0xab0005);
+    sim.set(PVT_INT, 0x3ff << THRESHOLD_HI_SHIFT | 0x12);
+    sim.set(PVT_STATUS, STATUS_VALID | 0x1ff);
+
+    let pvt = Pvt::read(&sim).unwrap();
+    assert_eq!(pvt.ctrl, 0xab0005);
+    assert_eq!((pvt.code, pvt.valid), (0x1ff, true));
+    assert_eq!((pvt.alarm_lo, pvt.alarm_hi), (0x12, 0x3ff));
+}
@@ -0,0 +1,333 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+//! Soft resets of individual subsystems.  Each MAC has soft reset registers
+//! of its own, whose fields are described in tf2.rsf.  The TM, the pipes and
+//! the PCIe controller are reset through misc_regs.soft_reset, but tf2.rsf
+//! describes soft_reset and dbg_rst only as opaque 32-bit fields, so the bit
+//! assigned to each of those subsystems must be supplied by the caller.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Error, Result, anyhow, bail};
+use serde::Serialize;
+
+use crate::backend::Backend;
+use crate::fuse::PIPES;
+use crate::port::MACS;
+
+const SOFT_RESET: u32 = 0x80000;
+const RESET_OPTION: u32 = 0x80004;
+const PCIECTL_RESET_CTRL: u32 = 0x80008;
+const DBG_RST: u32 = 0x8000c;
+/// pciectl_reset_ctrl.half_period
+const HALF_PERIOD_MASK: u32 = 0xfff;
+/// tm_top.tm_caa_top.block_ready and tm_top.tm_psc_top.psc_common
+/// .block_ready, and the number of words in each
+const TM_READY: [(u32, u32); 2] = [(0x880260, 6), (0xd20130, 3)];
+
+/// eth100g_regs.eth100g_reg.eth_soft_reset, for the CPU MAC
+const ETH100G_SOFT_RESET: u32 = 0x201fc14;
+/// eth400g_p1.eth400g_mac.eth_soft_reset and eth400g_p1.eth400g_pcs
+/// .eth_soft_reset, and the distance between each MAC's registers
+const ETH400G_MAC_SOFT_RESET: u32 = 0x2050014;
+const ETH400G_PCS_SOFT_RESET: u32 = 0x2050414;
+const ETH400G_STRIDE: u32 = 0x40000;
+/// The width of each of those registers' swrst field: eth_swrst is 4 bits
+/// in the eth100g MAC and 3 in the eth400g MACs, and ethpcs_swrst is 2.
+const ETH100G_SWRST_MASK: u32 = 0xf;
+const ETH400G_MAC_SWRST_MASK: u32 = 0x7;
+const ETH400G_PCS_SWRST_MASK: u32 = 0x3;
+
+/// How long a reset is held before being released
+const RESET_HOLD: Duration = Duration::from_millis(1);
+/// How long to wait for a subsystem to report that it's ready
+const READY_POLLS: u32 = 1000;
+const READY_POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+/// A subsystem which can be reset on its own
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Subsystem {
+    /// A MAC, numbered as in the fuse: 0 is the CPU MAC, and 1-32 are the
+    /// front-panel MACs
+    Mac(u32),
+    Tm,
+    Pipe(u32),
+    Pcie,
+}
+
+impl Subsystem {
+    /// Parse the name of a MAC: "aux" or "cpu" for the CPU MAC, or a number
+    /// between 1-32 for a front-panel MAC.
+    pub fn parse_mac(s: &str) -> Result<Self> {
+        match s.parse::<u32>() {
+            Ok(0) => bail!("the CPU MAC is named `cpu` or `aux`, not 0"),
+            Ok(n) if n >= MACS => {
+                bail!("MAC {n} is out of range (1-{})", MACS - 1)
+            }
+            Ok(n) => Ok(Subsystem::Mac(n)),
+            Err(_) => match s.to_lowercase().as_str() {
+                "aux" | "cpu" => Ok(Subsystem::Mac(0)),
+                _ => bail!("invalid MAC: {s}"),
+            },
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        match self {
+            Subsystem::Mac(m) if *m >= MACS => {
+                bail!("MAC {m} is out of range (0-{})", MACS - 1)
+            }
+            Subsystem::Pipe(p) if *p >= PIPES => {
+                bail!("pipe {p} is out of range (0-{})", PIPES - 1)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // The soft reset registers of a MAC, and the bits to set in each
+    fn mac_registers(mac: u32) -> Vec<(u32, u32)> {
+        match mac {
+            0 => vec![(ETH100G_SOFT_RESET, ETH100G_SWRST_MASK)],
+            m => {
+                let stride = ETH400G_STRIDE * (m - 1);
+                vec![
+                    (ETH400G_MAC_SOFT_RESET + stride, ETH400G_MAC_SWRST_MASK),
+                    (ETH400G_PCS_SOFT_RESET + stride, ETH400G_PCS_SWRST_MASK),
+                ]
+            }
+        }
+    }
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subsystem::Mac(0) => write!(f, "cpu"),
+            Subsystem::Mac(m) => write!(f, "mac{m}"),
+            Subsystem::Tm => write!(f, "tm"),
+            Subsystem::Pipe(p) => write!(f, "pipe{p}"),
+            Subsystem::Pcie => write!(f, "pcie"),
+        }
+    }
+}
+
+impl FromStr for Subsystem {
+    type Err = Error;
+
+    /// Parse "tm", "pcie", "pipe<n>", or the name of a MAC as accepted by
+    /// [`Subsystem::parse_mac`], optionally preceded by "mac".
+    fn from_str(s: &str) -> Result<Self> {
+        let sub = match s {
+            "tm" => Subsystem::Tm,
+            "pcie" => Subsystem::Pcie,
+            _ => match (s.strip_prefix("mac"), s.strip_prefix("pipe")) {
+                (Some(n), _) => Subsystem::parse_mac(n)?,
+                (_, Some(n)) => Subsystem::Pipe(
+                    n.parse().map_err(|_| anyhow!("invalid subsystem: {s}"))?,
+                ),
+                _ => Subsystem::parse_mac(s)
+                    .map_err(|_| anyhow!("invalid subsystem: {s}"))?,
+            },
+        };
+        sub.check()?;
+        Ok(sub)
+    }
+}
+
+// Poll until `ready` returns true.
+fn wait_ready<F>(sub: Subsystem, mut ready: F) -> Result<()>
+where
+    F: FnMut() -> Result<bool>,
+{
+    for _ in 0..READY_POLLS {
+        if ready()? {
+            return Ok(());
+        }
+        std::thread::sleep(READY_POLL_INTERVAL);
+    }
+    bail!("timed out waiting for {sub} to come out of reset")
+}
+
+fn read_tm_ready(pci: &dyn Backend) -> Result<Vec<u32>> {
+    let mut words = Vec::new();
+    for (offset, count) in TM_READY {
+        for word in 0..count {
+            words.push(pci.read4(offset + 4 * word)?);
+        }
+    }
+    Ok(words)
+}
+
+/// Reset a subsystem, holding it in reset briefly and then releasing it.  A
+/// MAC is reset through its own registers.  Anything else is reset through
+/// `soft_reset_bit` of misc_regs.soft_reset, which must be given, as
+/// described above.
+///
+/// Resetting the PCIe controller cuts the host off from the ASIC, so is
+/// refused unless `allow_pcie` is set.  Its reset isn't released until the
+/// ASIC answers reads again.  Once the TM's reset is released, this waits
+/// for each of its blocks that reported ready beforehand to do so again.
+/// The MACs and pipes give no indication of having come back out of reset,
+/// so this returns as soon as their resets have been released.
+pub fn reset(
+    pci: &dyn Backend,
+    sub: Subsystem,
+    soft_reset_bit: Option<u32>,
+    allow_pcie: bool,
+) -> Result<()> {
+    sub.check()?;
+    if sub == Subsystem::Pcie && !allow_pcie {
+        bail!(
+            "resetting the PCIe controller cuts off access to the ASIC; \
+             refusing without an override"
+        );
+    }
+    let regs = match (sub, soft_reset_bit) {
+        (Subsystem::Mac(mac), None) => Subsystem::mac_registers(mac),
+        (Subsystem::Mac(_), Some(_)) => {
+            bail!("MACs are reset through their own registers")
+        }
+        (_, Some(bit)) if bit < 32 => vec![(SOFT_RESET, 1 << bit)],
+        (_, Some(bit)) => bail!("soft_reset has no bit {bit}"),
+        (_, None) => bail!(
+            "the bit assigned to {sub} in misc_regs.soft_reset is not \
+             documented, so it must be given"
+        ),
+    };
+    let tm_ready = match sub {
+        Subsystem::Tm => read_tm_ready(pci)?,
+        _ => Vec::new(),
+    };
+
+    let mut held = Vec::new();
+    for (offset, mask) in &regs {
+        let val = mask | pci.read4(*offset)?;
+        pci.write4(*offset, val)?;
+        held.push(val);
+    }
+    std::thread::sleep(RESET_HOLD);
+    // Reads of an unreachable device return all ones.  The reset is released
+    // even if the ASIC never answers, in case writes still reach it, so each
+    // register is restored from the value written rather than read back.
+    let answered = match sub {
+        Subsystem::Pcie => {
+            wait_ready(sub, || Ok(pci.read4(SOFT_RESET)? != u32::MAX))
+        }
+        _ => Ok(()),
+    };
+    for ((offset, mask), val) in regs.iter().zip(held).rev() {
+        pci.write4(*offset, val & !mask)?;
+    }
+    answered?;
+    if sub == Subsystem::Tm {
+        wait_ready(sub, || {
+            let now = read_tm_ready(pci)?;
+            Ok(now.iter().zip(&tm_ready).all(|(n, t)| n & t == *t))
+        })?;
+    }
+    Ok(())
+}
+
+/// The contents of the reset control registers
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResetState {
+    pub soft_reset: u32,
+    pub reset_option: u32,
+    /// Half the length of a PCIe controller reset, in reference clocks
+    pub pcie_reset_half_period: u32,
+    pub dbg_rst: u32,
+    /// The MACs whose soft reset registers are holding them in reset
+    pub in_reset: Vec<Subsystem>,
+}
+
+impl ResetState {
+    pub fn read(pci: &dyn Backend) -> Result<Self> {
+        let mut in_reset = Vec::new();
+        for mac in 0..MACS {
+            for (offset, mask) in Subsystem::mac_registers(mac) {
+                if pci.read4(offset)? & mask != 0 {
+                    in_reset.push(Subsystem::Mac(mac));
+                    break;
+                }
+            }
+        }
+        Ok(ResetState {
+            soft_reset: pci.read4(SOFT_RESET)?,
+            reset_option: pci.read4(RESET_OPTION)?,
+            pcie_reset_half_period: pci.read4(PCIECTL_RESET_CTRL)?
+                & HALF_PERIOD_MASK,
+            dbg_rst: pci.read4(DBG_RST)?,
+            in_reset,
+        })
+    }
+}
+
+#[test]
+fn test_reset() {
+    use crate::sim::SimAsic;
+
+    assert_eq!("pipe3".parse::<Subsystem>().unwrap(), Subsystem::Pipe(3));
+    assert_eq!("mac32".parse::<Subsystem>().unwrap(), Subsystem::Mac(32));
+    assert_eq!("cpu".parse::<Subsystem>().unwrap(), Subsystem::Mac(0));
+    assert_eq!("7".parse::<Subsystem>().unwrap(), Subsystem::Mac(7));
+    assert_eq!(
+        Subsystem::Mac(0).to_string().parse::<Subsystem>().unwrap(),
+        Subsystem::Mac(0)
+    );
+    assert!("mac0".parse::<Subsystem>().is_err());
+    assert!(Subsystem::parse_mac("0").is_err());
+    assert!("mac33".parse::<Subsystem>().is_err());
+    assert!("pipe".parse::<Subsystem>().is_err());
+
+    // Record every write to MAC 5's registers, which keep their other bits.
+    let sim = SimAsic::new();
+    let mac = Subsystem::mac_registers(5);
+    assert_eq!(mac[1], (0x2150414, 0x3));
+    sim.set(mac[0].0, 0x100);
+    let writes = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    for (offset, _) in &mac {
+        let writes = writes.clone();
+        sim.on_write(*offset, move |regs, offset, val| {
+            writes.borrow_mut().push((offset, val));
+            regs.set(offset, val);
+        });
+    }
+    reset(&sim, Subsystem::Mac(5), None, false).unwrap();
+    assert_eq!(
+        *writes.borrow(),
+        [
+            (mac[0].0, 0x107),
+            (mac[1].0, 0x3),
+            (mac[1].0, 0x0),
+            (mac[0].0, 0x100)
+        ]
+    );
+
+    for sub in [Subsystem::Tm, Subsystem::Pipe(2), Subsystem::Pcie] {
+        assert!(reset(&sim, sub, None, true).is_err());
+    }
+    assert!(reset(&sim, Subsystem::Pcie, Some(3), false).is_err());
+    assert_eq!(sim.get(SOFT_RESET), 0);
+
+    // The TM's blocks stop reporting ready while it's held in reset.
+    let (caa, _) = TM_READY[0];
+    sim.set(caa, 0x3f);
+    sim.on_write(SOFT_RESET, move |regs, offset, val| {
+        regs.set(caa, if val & 1 << 9 != 0 { 0 } else { 0x3f });
+        regs.set(offset, val);
+    });
+    sim.set(SOFT_RESET, 0x1);
+    reset(&sim, Subsystem::Tm, Some(9), false).unwrap();
+    assert_eq!((sim.get(SOFT_RESET), sim.get(caa)), (0x1, 0x3f));
+
+    sim.set(mac[1].0, 0x1);
+    let state = ResetState::read(&sim).unwrap();
+    assert_eq!(state.in_reset, vec![Subsystem::Mac(5)]);
+}
@@ -0,0 +1,176 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+//! An in-memory model of the ASIC's register space, allowing code that
+//! operates on registers to be exercised without real hardware.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+
+use crate::REGISTER_SIZE;
+use crate::backend::Backend;
+
+/// Called in place of a normal read of a hooked register.  The hook is
+/// handed the register state and the offset being read, and returns the
+/// value to report.
+pub type ReadHook = Box<dyn FnMut(&mut SimRegs, u32) -> u32>;
+
+/// Called in place of a normal write to a hooked register.  The hook is
+/// handed the register state, the offset being written, and the value.  It
+/// is responsible for updating any state it wants the write to affect.
+pub type WriteHook = Box<dyn FnMut(&mut SimRegs, u32, u32)>;
+
+/// The contents of the simulated register space.  Only registers that have
+/// been written or given a reset value are stored; all others read as 0.
+#[derive(Default)]
+pub struct SimRegs {
+    values: BTreeMap<u32, u32>,
+    reset: BTreeMap<u32, u32>,
+}
+
+impl SimRegs {
+    /// Return the current value of the register at the given offset.
+    pub fn get(&self, offset: u32) -> u32 {
+        match self.values.get(&offset) {
+            Some(v) => *v,
+            None => self.reset.get(&offset).copied().unwrap_or(0),
+        }
+    }
+
+    /// Set the value of the register at the given offset.
+    pub fn set(&mut self, offset: u32, val: u32) {
+        self.values.insert(offset, val);
+    }
+}
+
+#[derive(Default)]
+struct SimState {
+    regs: SimRegs,
+    read_hooks: BTreeMap<u32, ReadHook>,
+    write_hooks: BTreeMap<u32, WriteHook>,
+}
+
+/// A simulated ASIC, modeling the 72 MiB register BAR
+#[derive(Default)]
+pub struct SimAsic {
+    state: RefCell<SimState>,
+}
+
+impl SimAsic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the values the given registers take after a reset.  Typically
+    /// these come from `regs::map::RegMap::reset_values`, which has them for
+    /// any register whose reset value is recorded in tf2.rsf.
+    pub fn with_reset_values(
+        self,
+        values: impl IntoIterator<Item = (u32, u32)>,
+    ) -> Self {
+        self.state.borrow_mut().regs.reset.extend(values);
+        self
+    }
+
+    /// Return every register to its reset value.
+    pub fn reset(&self) {
+        self.state.borrow_mut().regs.values.clear();
+    }
+
+    /// Set a register's value directly, bypassing any write hook.
+    pub fn set(&self, offset: u32, val: u32) {
+        self.state.borrow_mut().regs.set(offset, val);
+    }
+
+    /// Get a register's value directly, bypassing any read hook.
+    pub fn get(&self, offset: u32) -> u32 {
+        self.state.borrow().regs.get(offset)
+    }
+
+    /// Install a hook to be called when the given register is read.
+    pub fn on_read(
+        &self,
+        offset: u32,
+        hook: impl FnMut(&mut SimRegs, u32) -> u32 + 'static,
+    ) {
+        self.state.borrow_mut().read_hooks.insert(offset, Box::new(hook));
+    }
+
+    /// Install a hook to be called when the given register is written.
+    pub fn on_write(
+        &self,
+        offset: u32,
+        hook: impl FnMut(&mut SimRegs, u32, u32) + 'static,
+    ) {
+        self.state.borrow_mut().write_hooks.insert(offset, Box::new(hook));
+    }
+
+    fn check_offset(offset: u32) -> Result<()> {
+        if offset & 0x3 != 0 {
+            Err(anyhow!("unaligned 4-byte access at {}", offset))
+        } else if offset as usize + 4 > REGISTER_SIZE {
+            Err(anyhow!("offset {} is outside the mapped range", offset))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Backend for SimAsic {
+    fn read4(&self, offset: u32) -> Result<u32> {
+        Self::check_offset(offset)?;
+        let mut guard = self.state.borrow_mut();
+        let state = &mut *guard;
+        match state.read_hooks.get_mut(&offset) {
+            Some(hook) => Ok(hook(&mut state.regs, offset)),
+            None => Ok(state.regs.get(offset)),
+        }
+    }
+
+    fn write4(&self, offset: u32, val: u32) -> Result<()> {
+        Self::check_offset(offset)?;
+        let mut guard = self.state.borrow_mut();
+        let state = &mut *guard;
+        match state.write_hooks.get_mut(&offset) {
+            Some(hook) => hook(&mut state.regs, offset, val),
+            None => state.regs.set(offset, val),
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sim_hooks() {
+    let sim = SimAsic::new().with_reset_values([(0x10, 0xabcd)]);
+    assert_eq!(sim.read4(0x10).unwrap(), 0xabcd);
+    assert_eq!(sim.read4(0x14).unwrap(), 0);
+    sim.write4(0x10, 1).unwrap();
+    assert_eq!(sim.read4(0x10).unwrap(), 1);
+    sim.reset();
+    assert_eq!(sim.read4(0x10).unwrap(), 0xabcd);
+
+    // A write-1-to-clear status register
+    sim.set(0x20, 0xff);
+    sim.on_write(0x20, |regs, offset, val| {
+        regs.set(offset, regs.get(offset) & !val)
+    });
+    sim.write4(0x20, 0x0f).unwrap();
+    assert_eq!(sim.read4(0x20).unwrap(), 0xf0);
+
+    // A counter that advances each time it is read
+    sim.on_read(0x24, |regs, offset| {
+        let v = regs.get(offset) + 1;
+        regs.set(offset, v);
+        v
+    });
+    assert_eq!(sim.read4(0x24).unwrap(), 1);
+    assert_eq!(sim.read4(0x24).unwrap(), 2);
+
+    assert!(sim.read4(0x22).is_err());
+    assert!(sim.write4(REGISTER_SIZE as u32, 0).is_err());
+}
@@ -0,0 +1,307 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Copyright 2026 Oxide Computer Company
+
+//! Access to the boot flash through the SPI master in misc_regs.  Each
+//! transaction shifts out up to 8 bytes from spi_outdata0/1, opcode first,
+//! and then shifts up to 4 bytes into spi_indata.  Since chip select is
+//! only held for one transaction, flash is read and programmed at most 4
+//! bytes at a time.
+//!
+//! tf2.rsf describes spi_cmd only as an 8-bit field, and says nothing of the
+//! byte order of spi_idcode.  The layout of each used here has not been
+//! confirmed against a datasheet, so an [`Engine`] through which to drive
+//! transactions is only handed out when its caller explicitly overrides
+//! that.
+
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+
+use crate::backend::Backend;
+
+const SPI_OUTDATA0: u32 = 0x80120;
+const SPI_OUTDATA1: u32 = 0x80124;
+const SPI_COMMAND: u32 = 0x80128;
+const SPI_INDATA: u32 = 0x8012c;
+/// The JEDEC ID read by the boot firmware when it loaded
+const SPI_IDCODE: u32 = 0x80130;
+
+// spi_cmd, unconfirmed
+const CMD_OUT_MASK: u32 = 0xf;
+const CMD_IN_SHIFT: u32 = 4;
+const CMD_IN_MASK: u32 = 0x7;
+const CMD_START: u32 = 1 << 7;
+
+const MAX_OUT: usize = 8;
+const MAX_IN: usize = 4;
+
+// Flash opcodes
+const OP_WRITE_ENABLE: u8 = 0x06;
+const OP_READ_STATUS: u8 = 0x05;
+const OP_READ: u8 = 0x03;
+const OP_PAGE_PROGRAM: u8 = 0x02;
+const OP_SECTOR_ERASE: u8 = 0x20;
+const OP_READ_ID: u8 = 0x9f;
+
+/// Status register bit set while a program or erase is in progress
+const STATUS_WIP: u8 = 1 << 0;
+
+/// The flash is addressed with 3 bytes
+pub const ADDRESS_LIMIT: u32 = 1 << 24;
+/// The unit of erasure
+pub const SECTOR_SIZE: u32 = 4096;
+/// A single program may not cross a page boundary
+pub const PAGE_SIZE: u32 = 256;
+
+/// How long to wait for the engine to finish a transaction
+const ENGINE_POLLS: u32 = 100;
+const ENGINE_POLL_INTERVAL: Duration = Duration::from_micros(10);
+/// How long to wait for the flash to finish a program or erase
+const BUSY_POLLS: u32 = 5000;
+const BUSY_POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+/// The SPI engine, through which every transaction with the flash is made
+pub struct Engine<'a> {
+    pci: &'a dyn Backend,
+}
+
+impl<'a> Engine<'a> {
+    /// Drive the SPI engine through `pci`.  Since the engine's layout is
+    /// unconfirmed, this is refused unless `unconfirmed` is set.
+    pub fn new(pci: &'a dyn Backend, unconfirmed: bool) -> Result<Self> {
+        if !unconfirmed {
+            bail!("the SPI engine's layout is unconfirmed; refusing to use it");
+        }
+        Ok(Engine { pci })
+    }
+}
+
+/// Shift `out` to the flash, then shift in and return `in_len` bytes.
+pub fn transfer(spi: &Engine, out: &[u8], in_len: usize) -> Result<Vec<u8>> {
+    let pci = spi.pci;
+    if out.is_empty() || out.len() > MAX_OUT || in_len > MAX_IN {
+        bail!("invalid transfer: {} out, {in_len} in", out.len());
+    }
+    let mut words = [0u8; MAX_OUT];
+    words[..out.len()].copy_from_slice(out);
+    pci.write4(SPI_OUTDATA0, u32::from_le_bytes(words[..4].try_into()?))?;
+    pci.write4(SPI_OUTDATA1, u32::from_le_bytes(words[4..].try_into()?))?;
+    let cmd = (out.len() as u32 & CMD_OUT_MASK)
+        | ((in_len as u32 & CMD_IN_MASK) << CMD_IN_SHIFT)
+        | CMD_START;
+    pci.write4(SPI_COMMAND, cmd)?;
+
+    for _ in 0..ENGINE_POLLS {
+        if pci.read4(SPI_COMMAND)? & CMD_START == 0 {
+            let data = pci.read4(SPI_INDATA)?.to_le_bytes();
+            return Ok(data[..in_len].to_vec());
+        }
+        std::thread::sleep(ENGINE_POLL_INTERVAL);
+    }
+    bail!("timed out waiting for the SPI engine")
+}
+
+fn address(addr: u32) -> [u8; 3] {
+    let b = addr.to_be_bytes();
+    [b[1], b[2], b[3]]
+}
+
+fn check_range(addr: u32, len: usize) -> Result<()> {
+    if addr as u64 + len as u64 > ADDRESS_LIMIT as u64 {
+        bail!("{len} bytes at {addr:#x} extend past the end of the flash");
+    }
+    Ok(())
+}
+
+// Wait for the flash to finish a program or erase.
+fn wait_idle(spi: &Engine) -> Result<()> {
+    for _ in 0..BUSY_POLLS {
+        if transfer(spi, &[OP_READ_STATUS], 1)?[0] & STATUS_WIP == 0 {
+            return Ok(());
+        }
+        std::thread::sleep(BUSY_POLL_INTERVAL);
+    }
+    bail!("timed out waiting for the flash to become idle")
+}
+
+/// A JEDEC ID: the manufacturer, and the part's type and capacity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct JedecId {
+    pub manufacturer: u8,
+    pub memory_type: u8,
+    pub capacity: u8,
+}
+
+impl JedecId {
+    fn from_bytes(b: &[u8]) -> Self {
+        JedecId { manufacturer: b[0], memory_type: b[1], capacity: b[2] }
+    }
+
+    /// The size of the part in bytes, where the capacity code is the usual
+    /// log2 of it.
+    pub fn size(&self) -> Option<u64> {
+        (10..64).contains(&self.capacity).then(|| 1u64 << self.capacity)
+    }
+}
+
+/// Read the flash's JEDEC ID.
+pub fn read_id(spi: &Engine) -> Result<JedecId> {
+    Ok(JedecId::from_bytes(&transfer(spi, &[OP_READ_ID], 3)?))
+}
+
+/// Return the JEDEC ID recorded by the boot firmware when it loaded.
+pub fn boot_id(pci: &dyn Backend) -> Result<JedecId> {
+    Ok(JedecId::from_bytes(&pci.read4(SPI_IDCODE)?.to_le_bytes()))
+}
+
+/// Read `len` bytes starting at `addr`.
+pub fn read(spi: &Engine, addr: u32, len: usize) -> Result<Vec<u8>> {
+    check_range(addr, len)?;
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        let at = address(addr + data.len() as u32);
+        let n = (len - data.len()).min(MAX_IN);
+        data.extend(transfer(spi, &[OP_READ, at[0], at[1], at[2]], n)?);
+    }
+    Ok(data)
+}
+
+/// Erase the sector containing `addr`.
+pub fn erase_sector(spi: &Engine, addr: u32) -> Result<()> {
+    check_range(addr, 1)?;
+    let at = address(addr & !(SECTOR_SIZE - 1));
+    transfer(spi, &[OP_WRITE_ENABLE], 0)?;
+    transfer(spi, &[OP_SECTOR_ERASE, at[0], at[1], at[2]], 0)?;
+    wait_idle(spi)
+}
+
+/// Program `data` at `addr`, which must already have been erased.
+pub fn program(spi: &Engine, addr: u32, data: &[u8]) -> Result<()> {
+    check_range(addr, data.len())?;
+    let mut done = 0;
+    while done < data.len() {
+        let cur = addr + done as u32;
+        // Each transaction has room for 4 bytes of data after the opcode and
+        // address, and must not wrap around the end of a page.
+        let to_page = (PAGE_SIZE - cur % PAGE_SIZE) as usize;
+        let n = (data.len() - done).min(MAX_OUT - 4).min(to_page);
+        let at = address(cur);
+        let mut out = vec![OP_PAGE_PROGRAM, at[0], at[1], at[2]];
+        out.extend_from_slice(&data[done..done + n]);
+        transfer(spi, &[OP_WRITE_ENABLE], 0)?;
+        transfer(spi, &out, 0)?;
+        wait_idle(spi)?;
+        done += n;
+    }
+    Ok(())
+}
+
+/// Erase the sectors covering `data` and program it at `addr`, which must
+/// be the start of a sector.  Any part of the last sector beyond the end of
+/// the data is left erased.  This is refused if the flash's ID differs
+/// from the one read by the boot firmware.
+pub fn write(spi: &Engine, addr: u32, data: &[u8]) -> Result<()> {
+    if !addr.is_multiple_of(SECTOR_SIZE) {
+        bail!("{addr:#x} is not the start of a {SECTOR_SIZE}-byte sector");
+    }
+    check_range(addr, data.len())?;
+    let (id, boot) = (read_id(spi)?, boot_id(spi.pci)?);
+    if id != boot {
+        bail!("flash ID {id:?} differs from the boot firmware's {boot:?}");
+    }
+    let mut sector = addr;
+    while sector < addr + data.len() as u32 {
+        erase_sector(spi, sector)?;
+        sector += SECTOR_SIZE;
+    }
+    program(spi, addr, data)
+}
+
+/// Compare the flash at `addr` with `data`, returning the address of the
+/// first byte that differs.
+pub fn verify(spi: &Engine, addr: u32, data: &[u8]) -> Result<Option<u32>> {
+    let found = read(spi, addr, data.len())?;
+    Ok(found
+        .iter()
+        .zip(data)
+        .position(|(a, b)| a != b)
+        .map(|i| addr + i as u32))
+}
+
+#[test]
+fn test_spi_flash() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::sim::SimAsic;
+
+    // A flash device behind the SPI engine, which completes every
+    // transaction as soon as it is started.
+    let flash = Rc::new(RefCell::new(vec![0xffu8; 2 * SECTOR_SIZE as usize]));
+    let dev = flash.clone();
+    let mut write_enabled = false;
+    let sim = SimAsic::new();
+    sim.set(SPI_IDCODE, 0x18_40_ef);
+    sim.on_write(SPI_COMMAND, move |regs, offset, cmd| {
+        let mut out = regs.get(SPI_OUTDATA0).to_le_bytes().to_vec();
+        out.extend(regs.get(SPI_OUTDATA1).to_le_bytes());
+        out.truncate((cmd & CMD_OUT_MASK) as usize);
+        let in_len = ((cmd >> CMD_IN_SHIFT) & CMD_IN_MASK) as usize;
+        let addr = match out.len() {
+            4.. => u32::from_be_bytes([0, out[1], out[2], out[3]]) as usize,
+            _ => 0,
+        };
+        let mut flash = dev.borrow_mut();
+        let mut indata = [0u8; 4];
+        match out[0] {
+            OP_READ_ID => indata[..3].copy_from_slice(&[0xef, 0x40, 0x18]),
+            OP_READ_STATUS => {}
+            OP_READ => {
+                indata[..in_len].copy_from_slice(&flash[addr..addr + in_len])
+            }
+            OP_WRITE_ENABLE => write_enabled = true,
+            OP_SECTOR_ERASE if write_enabled => {
+                flash[addr..addr + SECTOR_SIZE as usize].fill(0xff);
+                write_enabled = false;
+            }
+            OP_PAGE_PROGRAM if write_enabled => {
+                for (i, b) in out[4..].iter().enumerate() {
+                    flash[addr + i] &= b;
+                }
+                write_enabled = false;
+            }
+            _ => {}
+        }
+        regs.set(SPI_INDATA, u32::from_le_bytes(indata));
+        regs.set(offset, cmd & !CMD_START);
+    });
+
+    assert!(Engine::new(&sim, false).is_err());
+    let spi = Engine::new(&sim, true).unwrap();
+    let id = read_id(&spi).unwrap();
+    assert_eq!(id, boot_id(&sim).unwrap());
+    assert_eq!(id.size(), Some(16 << 20));
+
+    let image: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+    flash.borrow_mut()[..8].fill(0);
+    assert_eq!(read(&spi, 0, 1).unwrap(), [0]);
+    write(&spi, 0, &image).unwrap();
+    assert_eq!(verify(&spi, 0, &image).unwrap(), None);
+    assert_eq!(read(&spi, 298, 3).unwrap(), vec![image[298], image[299], 0xff]);
+
+    // Programming without erasing can only clear bits.
+    program(&spi, 2, &[0xff]).unwrap();
+    program(&spi, 1, &[0]).unwrap();
+    assert_eq!(verify(&spi, 0, &image).unwrap(), Some(1));
+    assert!(write(&spi, 1, &image).is_err());
+    assert!(read(&spi, ADDRESS_LIMIT - 2, 4).is_err());
+
+    // A flash other than the one the firmware booted from is left alone.
+    sim.set(SPI_IDCODE, 0x17_40_ef);
+    assert!(write(&spi, 0, &[0; 4]).is_err());
+    assert_eq!(verify(&spi, 0, &image[..2]).unwrap(), Some(1));
+}
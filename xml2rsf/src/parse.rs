@@ -95,11 +95,13 @@ pub enum AccessMode {
     WriteOnly,
     ReadWrite,
     ReadWrite1Clear,
+    ReadClear,
 }
 
 fn access_parse(s: &str) -> Result<AccessMode> {
     match s.to_lowercase().as_str() {
-        "r" | "rc" => Ok(AccessMode::ReadOnly),
+        "r" => Ok(AccessMode::ReadOnly),
+        "rc" => Ok(AccessMode::ReadClear),
         "w" => Ok(AccessMode::WriteOnly),
         "rw" | "r/w" => Ok(AccessMode::ReadWrite),
         "r/w1c" => Ok(AccessMode::ReadWrite1Clear),
@@ -412,3 +414,33 @@ pub fn parse_xml(xml: &String) -> Result<RegMap> {
         bail!("unrecognized register map");
     }
 }
+
+#[test]
+fn test_access_parse() {
+    let val = |name: &str, value: &str| RawNode::Value {
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+
+    assert_eq!(access_parse("r").unwrap(), AccessMode::ReadOnly);
+    assert_eq!(access_parse("RC").unwrap(), AccessMode::ReadClear);
+    assert_eq!(access_parse("w").unwrap(), AccessMode::WriteOnly);
+    assert_eq!(access_parse("rw").unwrap(), AccessMode::ReadWrite);
+    assert_eq!(access_parse("R/W").unwrap(), AccessMode::ReadWrite);
+    assert_eq!(access_parse("R/W1C").unwrap(), AccessMode::ReadWrite1Clear);
+    assert!(access_parse("w1s").is_err());
+
+    let node = RawNode::Container {
+        name: "bitfield".to_string(),
+        children: vec![
+            val("identifier", "IntStatus"),
+            val("access", "r/w1c"),
+            val("lsb", "4"),
+            val("msb", "7"),
+        ],
+    };
+    let b = Bitfield::try_from(&node).unwrap();
+    assert_eq!(b.id, "int_status");
+    assert_eq!(b.access, AccessMode::ReadWrite1Clear);
+    assert_eq!((b.lsb, b.msb), (4, 7));
+}
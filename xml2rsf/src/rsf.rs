@@ -349,15 +349,27 @@ fn register_to_rsf(idx: &Indexes, register: &Register) -> ast::Register {
         .iter()
         .map(|f| {
             let id = ast::Identifier::new(&f.id);
+            // rsf has no modes for write-1-to-clear or clear-on-read fields,
+            // so they are emitted as the nearest plain mode, with the real
+            // mode recorded in the doc comment for regs::map to pick up.
+            let mut doc = vec![f.id.to_string()];
+            let mode = match f.access {
+                AccessMode::ReadOnly => FieldMode::ReadOnly,
+                AccessMode::ReadWrite => FieldMode::ReadWrite,
+                AccessMode::ReadWrite1Clear => {
+                    doc.push("access: w1c".to_string());
+                    FieldMode::ReadWrite
+                }
+                AccessMode::ReadClear => {
+                    doc.push("access: rc".to_string());
+                    FieldMode::ReadOnly
+                }
+                AccessMode::WriteOnly => FieldMode::WriteOnly,
+            };
             ast::Field {
                 id: id.clone(),
-                doc: vec![f.id.to_string()],
-                mode: match f.access {
-                    AccessMode::ReadOnly => FieldMode::ReadOnly,
-                    AccessMode::ReadWrite => FieldMode::ReadWrite,
-                    AccessMode::ReadWrite1Clear => FieldMode::ReadWrite,
-                    AccessMode::WriteOnly => FieldMode::WriteOnly,
-                },
+                doc,
+                mode,
                 typ: FieldType::Bitfield { width: ast_hex(f.msb - f.lsb + 1) },
                 offset: ast_hex(f.lsb),
                 attrs: Vec::new(),
@@ -509,3 +521,50 @@ pub fn convert(map: RegMap) -> Result<ast::Ast> {
     };
     Ok(ast)
 }
+
+#[test]
+fn test_register_access() {
+    let field = |id: &str, access, lsb| Bitfield {
+        id: id.to_string(),
+        access,
+        lsb,
+        msb: lsb,
+    };
+    let register = Register {
+        ref_name: "ref".to_string(),
+        id: "int_stat".to_string(),
+        title: "Interrupt status".to_string(),
+        access: AccessMode::ReadWrite,
+        offset: 0,
+        reset_value: Some(0x10),
+        reset_mask: None,
+        bitfields: vec![
+            field("pending", AccessMode::ReadWrite1Clear, 1),
+            field("_", AccessMode::ReadOnly, 2),
+            field("count", AccessMode::ReadClear, 3),
+            field("enable", AccessMode::ReadWrite, 0),
+        ],
+    };
+    let idx = Indexes {
+        map: RegMap { address_maps: Vec::new(), registers: Vec::new() },
+        registers: [("ref".to_string(), "IntStat".to_string())].into(),
+        blocks: BTreeMap::new(),
+    };
+
+    let r = register_to_rsf(&idx, &register);
+    assert_eq!(r.id.name, "IntStat");
+    assert_eq!(r.reset_value.map(|n| n.value), Some(0x10));
+
+    let [enable, pending, count] = &r.fields[..] else {
+        panic!("expected three fields, got {}", r.fields.len());
+    };
+    assert_eq!(enable.id.name, "enable");
+    assert!(matches!(enable.mode, FieldMode::ReadWrite));
+    assert_eq!(enable.doc, ["enable"]);
+    assert_eq!(pending.id.name, "pending");
+    assert!(matches!(pending.mode, FieldMode::ReadWrite));
+    assert_eq!(pending.doc, ["pending", "access: w1c"]);
+    assert_eq!(count.id.name, "count");
+    assert!(matches!(count.mode, FieldMode::ReadOnly));
+    assert_eq!(count.doc, ["count", "access: rc"]);
+}